@@ -0,0 +1,436 @@
+//! Attachments with the metadata clients need to lay them out before downloading them:
+//! dimensions, duration, the waveform of voice notes and a small thumbnail
+//!
+//! An attachment is sent as a `ProcessAll` multipart with the `attachment` disposition:
+//!
+//! ```text
+//! ProcessAll multipart (attachment)
+//! ├── external part                                          the file
+//! ├── image/* (preview)                                      the thumbnail, optional
+//! └── application/vnd.mimi-content.attachment-metadata+cbor  the metadata, optional
+//! ```
+//!
+//! The metadata is a CBOR map with integer keys: `1` width and `2` height in pixels, `3`
+//! duration in milliseconds, `4` waveform as a byte string of amplitudes. Unknown keys are
+//! ignored. Clients that don't know the metadata type skip it, as allowed by `ProcessAll`.
+
+use ciborium::Value;
+
+use crate::{
+    media_type::media_type, BaseDispos, Disposition, ExternalPart, MediaType, MimiContent,
+    MimiContentError, MultiPart, NestedPart, NestedPartContent, PartInfo, PartSemantics,
+    SinglePart, Walk,
+};
+
+pub const ATTACHMENT_METADATA: MediaType = media_type(
+    "application",
+    "vnd.mimi-content.attachment-metadata+cbor",
+    &[],
+);
+
+const WIDTH: i64 = 1;
+const HEIGHT: i64 = 2;
+const DURATION_MS: i64 = 3;
+const WAVEFORM: i64 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Image,
+    Video,
+    Audio,
+    File,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct AttachmentMetadata {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub duration_ms: Option<u64>,
+    /// Amplitudes of the audio, from `0` (silence) to `255`, evenly spread over its duration
+    pub waveform: Option<Vec<u8>>,
+}
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidAttachment(reason)
+}
+
+impl AttachmentMetadata {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn to_cbor_bytes(&self) -> Result<Vec<u8>, MimiContentError> {
+        let entries = [
+            (WIDTH, self.width.map(Value::from)),
+            (HEIGHT, self.height.map(Value::from)),
+            (DURATION_MS, self.duration_ms.map(Value::from)),
+            (WAVEFORM, self.waveform.clone().map(Value::Bytes)),
+        ];
+        let map = entries
+            .into_iter()
+            .filter_map(|(key, value)| Some((Value::from(key), value?)))
+            .collect();
+        let mut bytes = vec![];
+        ciborium::into_writer(&Value::Map(map), &mut bytes)?;
+        Ok(bytes)
+    }
+
+    pub fn from_cbor_bytes(bytes: &[u8]) -> Result<Self, MimiContentError> {
+        let Value::Map(entries) = ciborium::from_reader(bytes)? else {
+            return Err(invalid("the metadata is not a map"));
+        };
+        let mut metadata = Self::default();
+        for (key, value) in entries {
+            let Some(key) = key.as_integer().and_then(|key| i64::try_from(key).ok()) else {
+                continue;
+            };
+            let integer = || value.as_integer().ok_or(invalid("expected an integer"));
+            match key {
+                WIDTH => {
+                    metadata.width = Some(
+                        integer()?
+                            .try_into()
+                            .map_err(|_| invalid("invalid width"))?,
+                    )
+                }
+                HEIGHT => {
+                    metadata.height = Some(
+                        integer()?
+                            .try_into()
+                            .map_err(|_| invalid("invalid height"))?,
+                    )
+                }
+                DURATION_MS => {
+                    metadata.duration_ms = Some(
+                        integer()?
+                            .try_into()
+                            .map_err(|_| invalid("invalid duration"))?,
+                    )
+                }
+                WAVEFORM => {
+                    let Value::Bytes(waveform) = value else {
+                        return Err(invalid("the waveform is not a byte string"));
+                    };
+                    metadata.waveform = Some(waveform);
+                }
+                _ => {}
+            }
+        }
+        Ok(metadata)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub file: ExternalPart,
+    pub metadata: AttachmentMetadata,
+    /// A small image shown until the file is downloaded
+    pub thumbnail: Option<SinglePart>,
+}
+
+#[bon::bon]
+impl Attachment {
+    #[builder(finish_fn = build)]
+    pub fn image(
+        file: ExternalPart,
+        width: u32,
+        height: u32,
+        thumbnail: Option<SinglePart>,
+    ) -> Self {
+        Self {
+            file,
+            metadata: AttachmentMetadata {
+                width: Some(width),
+                height: Some(height),
+                ..Default::default()
+            },
+            thumbnail,
+        }
+    }
+
+    #[builder(finish_fn = build)]
+    pub fn video(
+        file: ExternalPart,
+        width: u32,
+        height: u32,
+        duration_ms: u64,
+        thumbnail: Option<SinglePart>,
+    ) -> Self {
+        Self {
+            file,
+            metadata: AttachmentMetadata {
+                width: Some(width),
+                height: Some(height),
+                duration_ms: Some(duration_ms),
+                waveform: None,
+            },
+            thumbnail,
+        }
+    }
+
+    /// Audio, such as a voice note with its `waveform`
+    #[builder(finish_fn = build)]
+    pub fn audio(file: ExternalPart, duration_ms: u64, waveform: Option<Vec<u8>>) -> Self {
+        Self {
+            file,
+            metadata: AttachmentMetadata {
+                duration_ms: Some(duration_ms),
+                waveform,
+                ..Default::default()
+            },
+            thumbnail: None,
+        }
+    }
+
+    #[builder(finish_fn = build)]
+    pub fn file(file: ExternalPart, thumbnail: Option<SinglePart>) -> Self {
+        Self {
+            file,
+            metadata: AttachmentMetadata::default(),
+            thumbnail,
+        }
+    }
+}
+
+impl Attachment {
+    /// From the content type of the file
+    pub fn kind(&self) -> AttachmentKind {
+        let Ok(media_type) = self.file.media_type() else {
+            return AttachmentKind::File;
+        };
+        match media_type.type_().to_ascii_lowercase().as_str() {
+            "image" => AttachmentKind::Image,
+            "video" => AttachmentKind::Video,
+            "audio" => AttachmentKind::Audio,
+            _ => AttachmentKind::File,
+        }
+    }
+
+    /// The part to send, with the `attachment` disposition
+    pub fn to_nested_part(&self) -> Result<NestedPart, MimiContentError> {
+        let mut parts = vec![NestedPart::builder()
+            .part_content(NestedPartContent::ExternalPart(self.file.clone()))
+            .build()];
+        if let Some(thumbnail) = &self.thumbnail {
+            parts.push(
+                NestedPart::builder()
+                    .disposition(Disposition::Base(BaseDispos::Preview))
+                    .part_content(NestedPartContent::SinglePart(thumbnail.clone()))
+                    .build(),
+            );
+        }
+        if !self.metadata.is_empty() {
+            let metadata = SinglePart {
+                content_type: ATTACHMENT_METADATA.into(),
+                content: self.metadata.to_cbor_bytes()?.into(),
+            };
+            parts.push(
+                NestedPart::builder()
+                    .part_content(NestedPartContent::SinglePart(metadata))
+                    .build(),
+            );
+        }
+        Ok(NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Attachment))
+            .part_content(NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts,
+            }))
+            .build())
+    }
+
+    /// Reads an attachment part, as produced by [`Self::to_nested_part`], or a lone external
+    /// part without metadata (its disposition isn't checked)
+    pub fn from_nested_part(part: &NestedPart) -> Result<Self, MimiContentError> {
+        let multi = match &part.part_content {
+            NestedPartContent::ExternalPart(file) => {
+                return Ok(Self {
+                    file: file.clone(),
+                    metadata: AttachmentMetadata::default(),
+                    thumbnail: None,
+                })
+            }
+            NestedPartContent::MultiPart(multi)
+                if multi.part_semantics == PartSemantics::ProcessAll =>
+            {
+                multi
+            }
+            _ => return Err(invalid("not an external part or a ProcessAll multipart")),
+        };
+        let Some((file, mut rest)) = multi.parts.split_first() else {
+            return Err(invalid("no file"));
+        };
+        let NestedPartContent::ExternalPart(file) = &file.part_content else {
+            return Err(invalid("the file is not an external part"));
+        };
+
+        let mut attachment = Self {
+            file: file.clone(),
+            metadata: AttachmentMetadata::default(),
+            thumbnail: None,
+        };
+        if let [part, tail @ ..] = rest {
+            if let (Disposition::Base(BaseDispos::Preview), NestedPartContent::SinglePart(single)) =
+                (part.disposition, &part.part_content)
+            {
+                attachment.thumbnail = Some(single.clone());
+                rest = tail;
+            }
+        }
+        if let [part, tail @ ..] = rest {
+            if let NestedPartContent::SinglePart(single) = &part.part_content {
+                if single
+                    .media_type()
+                    .is_ok_and(|media_type| media_type.same_essence(&ATTACHMENT_METADATA))
+                {
+                    attachment.metadata = AttachmentMetadata::from_cbor_bytes(&single.content)?;
+                    rest = tail;
+                }
+            }
+        }
+        if !rest.is_empty() {
+            return Err(invalid(
+                "expected a file, an optional thumbnail and optional metadata",
+            ));
+        }
+        Ok(attachment)
+    }
+}
+
+impl MimiContent {
+    /// The attachments among the parts with the `attachment` disposition, set on them or
+    /// inherited, e.g. the photos of an album
+    pub fn attachments(&self) -> Vec<Attachment> {
+        let mut attachments = vec![];
+        self.accept(&mut |part: &NestedPart, info: &PartInfo<'_>| {
+            if info.disposition != Disposition::Base(BaseDispos::Attachment) {
+                return Walk::Continue;
+            }
+            match Attachment::from_nested_part(part) {
+                Ok(attachment) => {
+                    attachments.push(attachment);
+                    Walk::SkipChildren
+                }
+                Err(_) => Walk::Continue,
+            }
+        });
+        attachments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MimiContentDeserialize as _;
+
+    fn external(content_type: &str) -> ExternalPart {
+        let mimi_content =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/attachment.cbor"))
+                .unwrap();
+        let NestedPartContent::ExternalPart(external) = mimi_content.nested_part.part_content
+        else {
+            panic!("expected an external part");
+        };
+        ExternalPart {
+            content_type: content_type.into(),
+            ..external
+        }
+    }
+
+    #[test]
+    fn roundtrips_metadata() {
+        let metadata = AttachmentMetadata {
+            width: Some(4032),
+            duration_ms: Some(1500),
+            waveform: Some(vec![0, 128, 255]),
+            ..Default::default()
+        };
+        let bytes = metadata.to_cbor_bytes().unwrap();
+        assert_eq!(
+            bytes,
+            b"\xA3\x01\x19\x0F\xC0\x03\x19\x05\xDC\x04\x43\x00\x80\xFF"
+        );
+        assert_eq!(
+            AttachmentMetadata::from_cbor_bytes(&bytes).unwrap(),
+            metadata
+        );
+
+        // unknown keys are ignored
+        assert_eq!(
+            AttachmentMetadata::from_cbor_bytes(b"\xA2\x02\x18\x2A\x18\x63\x60").unwrap(),
+            AttachmentMetadata {
+                height: Some(42),
+                ..Default::default()
+            }
+        );
+        assert!(AttachmentMetadata::from_cbor_bytes(b"\xA1\x04\x01").is_err());
+    }
+
+    #[test]
+    fn gathers_an_album_and_a_voice_note() {
+        let thumbnail = SinglePart {
+            content_type: "image/png".into(),
+            content: b"\x89PNG\r\n\x1a\n".to_vec().into(),
+        };
+        let photo = Attachment::image()
+            .file(external("image/jpeg"))
+            .width(4032)
+            .height(3024)
+            .thumbnail(thumbnail)
+            .build();
+        let voice_note = Attachment::audio()
+            .file(external("audio/ogg"))
+            .duration_ms(4200)
+            .waveform(vec![3, 80, 255, 17])
+            .build();
+        assert_eq!(photo.kind(), AttachmentKind::Image);
+        assert_eq!(voice_note.kind(), AttachmentKind::Audio);
+
+        let album = NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Attachment))
+            .part_content(NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts: vec![
+                    photo.to_nested_part().unwrap(),
+                    voice_note.to_nested_part().unwrap(),
+                ],
+            }))
+            .build();
+        let mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy(Default::default())
+            .topic_id(Default::default())
+            .nested_part(album)
+            .build();
+        assert_eq!(mimi_content.attachments(), vec![photo, voice_note]);
+
+        let plain_album = NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Attachment))
+            .part_content(NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts: ["image/jpeg", "image/png", "image/gif"]
+                    .map(|content_type| {
+                        NestedPart::builder()
+                            .part_content(NestedPartContent::ExternalPart(external(content_type)))
+                            .build()
+                    })
+                    .to_vec(),
+            }))
+            .build();
+        assert!(Attachment::from_nested_part(&plain_album).is_err());
+        let plain_album = MimiContent::builder()
+            .salt_from_outside_entropy(Default::default())
+            .topic_id(Default::default())
+            .nested_part(plain_album)
+            .build()
+            .attachments();
+        assert_eq!(plain_album.len(), 3);
+        assert_eq!(&*plain_album[2].file.content_type, "image/gif");
+
+        let spec_example =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/attachment.cbor"))
+                .unwrap();
+        let [attachment] = spec_example.attachments().try_into().unwrap();
+        assert!(attachment.metadata.is_empty());
+        assert_eq!(attachment.kind(), AttachmentKind::Video);
+    }
+}
@@ -0,0 +1,348 @@
+//! Command-line tool to inspect, build and verify MIMI content messages
+
+use std::process::ExitCode;
+
+use mimi_content::{
+    edn::MimiContentEdn as _,
+    gfm_mimi::GfmMimiRenderer,
+    json::MimiContentJson as _,
+    reaction,
+    safety::{FilenamePolicy, UrlPolicy},
+    sniff::SniffVerdict,
+    BaseDispos, Disposition, Expiration, MediaType, MessageId, MimiContent, MimiContentAsRef as _,
+    MimiContentDeserialize as _, MimiContentSerialize as _, NestedPart, NestedPartContent,
+    PathPart, SinglePart, Tstr,
+};
+
+const USAGE: &str = "\
+Usage: mimi-content <COMMAND> [ARGS]
+
+Commands:
+  inspect <FILE> [--json]                 Print a CBOR message as diagnostic notation (or JSON)
+  validate <FILE>                         Check that a CBOR message decodes and re-encodes identically
+  message-id <FILE> <SENDER> <ROOM>       Compute the message id for the given sender and room URIs
+  franking-tag <FILE>                     Compute the franking tag of a message
+  render <FILE> [--html | --text]         Render the GFM-MIMI parts of a message to HTML or plain text
+  build <DESCRIPTION> <OUT>               Build a CBOR message from a text description
+
+A description starts with `key: value` headers, followed by a blank line and the body:
+
+  sender: mimi://example.com/u/alice-smith
+  room: mimi://example.com/r/engineering_team
+  content-type: text/markdown;variant=GFM-MIMI
+  language: en
+  disposition: render
+  in-reply-to: <hex message id>
+  replaces: <hex message id>
+  topic: <text>
+  expires: relative <seconds> | absolute <seconds since epoch>
+
+  Hi everyone, we just shipped release 2.0!
+
+The body of a reaction may use shortcodes such as `:+1:`.
+";
+
+type CliResult<T> = Result<T, String>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["inspect", file] => inspect(file, false),
+        ["inspect", file, "--json"] => inspect(file, true),
+        ["validate", file] => validate(file),
+        ["message-id", file, sender, room] => message_id(file, sender, room),
+        ["franking-tag", file] => franking_tag(file),
+        ["render", file] => render(file, true),
+        ["render", file, "--html"] => render(file, true),
+        ["render", file, "--text"] => render(file, false),
+        ["build", description, out] => build(description, out),
+        _ => {
+            eprint!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn read_content(file: &str) -> CliResult<(Vec<u8>, MimiContent)> {
+    let bytes = std::fs::read(file).map_err(|e| format!("cannot read {file}: {e}"))?;
+    let mimi_content =
+        MimiContent::from_cbor_bytes(&bytes).map_err(|e| format!("cannot decode {file}: {e}"))?;
+    Ok((bytes, mimi_content))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> CliResult<Vec<u8>> {
+    if !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("{text:?} is not hexadecimal"));
+    }
+    if !text.len().is_multiple_of(2) {
+        return Err(format!("{text:?} has an odd number of hex digits"));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("{text:?} is not hexadecimal"))
+}
+
+fn inspect(file: &str, json: bool) -> CliResult<()> {
+    let (_, mimi_content) = read_content(file)?;
+    let output = if json {
+        mimi_content.to_json_pretty()
+    } else {
+        mimi_content.to_edn_pretty().map_err(|e| e.to_string())?
+    };
+    println!("{output}");
+    Ok(())
+}
+
+fn collect_problems(mimi_content: &MimiContent, problems: &mut Vec<String>) {
+    for PathPart {
+        path,
+        disposition,
+        part,
+        ..
+    } in mimi_content.iter_parts()
+    {
+        let path: String = path.iter().map(|index| format!("/{index}")).collect();
+        let path = format!("nestedPart{path}");
+        match &part.part_content {
+            NestedPartContent::NullPart => {}
+            NestedPartContent::SinglePart(single) => {
+                if single.content_type.is_empty() {
+                    problems.push(format!("{path}: single part without a content type"));
+                } else if let Ok(SniffVerdict::Mismatch { detected, .. }) =
+                    single.check_content_type()
+                {
+                    problems.push(format!(
+                        "{path}: declared as {:?} but looks like {detected}",
+                        single.content_type
+                    ));
+                }
+                if disposition == Disposition::Base(BaseDispos::Reaction) {
+                    if let Err(e) = single.reaction_emoji() {
+                        problems.push(format!("{path}: {e}"));
+                    }
+                }
+            }
+            NestedPartContent::ExternalPart(external) => {
+                if external.url.is_empty() {
+                    problems.push(format!("{path}: external part without a URL"));
+                } else if let Err(violation) = UrlPolicy::default().check(&external.url) {
+                    problems.push(format!("{path}: {violation}"));
+                }
+                let filename = external.sanitized_filename(&FilenamePolicy::default());
+                if !external.filename.is_empty() && !filename.is_unchanged() {
+                    problems.push(format!(
+                        "{path}: unsafe filename {:?}, saved as {:?}",
+                        external.filename, filename.filename
+                    ));
+                }
+            }
+            NestedPartContent::Unknown { cardinality, .. } => {
+                problems.push(format!("{path}: unknown cardinality {cardinality}"));
+            }
+            NestedPartContent::MultiPart(multi) => {
+                if multi.parts.is_empty() {
+                    problems.push(format!("{path}: multipart without parts"));
+                }
+            }
+        }
+    }
+}
+
+fn validate(file: &str) -> CliResult<()> {
+    let (bytes, mimi_content) = read_content(file)?;
+    let mut problems = vec![];
+
+    let reencoded = mimi_content.to_cbor_bytes().map_err(|e| e.to_string())?;
+    if reencoded != bytes {
+        problems.push("the message doesn't re-encode to the same bytes".to_string());
+    }
+    if mimi_content
+        .as_ref()
+        .to_cbor_bytes()
+        .map_err(|e| e.to_string())?
+        != reencoded
+    {
+        problems.push("the zero-copy encoding differs from the owned encoding".to_string());
+    }
+    collect_problems(&mimi_content, &mut problems);
+
+    if problems.is_empty() {
+        println!("{file}: valid");
+        Ok(())
+    } else {
+        for problem in &problems {
+            println!("{file}: {problem}");
+        }
+        Err(format!("{} problem(s) found", problems.len()))
+    }
+}
+
+fn message_id(file: &str, sender: &str, room: &str) -> CliResult<()> {
+    let (_, mimi_content) = read_content(file)?;
+    let message_id = MessageId::construct(
+        Tstr::from(sender).as_ref(),
+        Tstr::from(room).as_ref(),
+        &mimi_content,
+    )
+    .map_err(|e| e.to_string())?;
+    println!("{}", hex(&*message_id));
+    Ok(())
+}
+
+fn franking_tag(file: &str) -> CliResult<()> {
+    let (_, mimi_content) = read_content(file)?;
+    let franking_tag = mimi_content
+        .calculate_franking_tag()
+        .map_err(|e| e.to_string())?;
+    println!("{}", hex(&franking_tag.into_inner()));
+    Ok(())
+}
+
+fn render(file: &str, html: bool) -> CliResult<()> {
+    let (_, mimi_content) = read_content(file)?;
+    let renderer = GfmMimiRenderer::new();
+    for PathPart { part, .. } in mimi_content.iter_parts() {
+        let NestedPartContent::SinglePart(single) = &part.part_content else {
+            continue;
+        };
+        if !single
+            .media_type()
+            .is_ok_and(|media_type| media_type.same_essence(&MediaType::TEXT_MARKDOWN_GFM_MIMI))
+        {
+            continue;
+        }
+        let markdown = single
+            .text()
+            .unwrap_or_else(|_| String::from_utf8_lossy(&single.content));
+        if html {
+            print!("{}", renderer.gfm_mimi_to_html(&markdown));
+        } else {
+            print!("{}", renderer.gfm_mimi_to_plain_text(&markdown));
+        }
+    }
+    Ok(())
+}
+
+fn parse_disposition(text: &str) -> CliResult<Disposition> {
+    let base = match text {
+        "unspecified" => BaseDispos::Unspecified,
+        "render" => BaseDispos::Render,
+        "reaction" => BaseDispos::Reaction,
+        "profile" => BaseDispos::Profile,
+        "inline" => BaseDispos::Inline,
+        "icon" => BaseDispos::Icon,
+        "attachment" => BaseDispos::Attachment,
+        "session" => BaseDispos::Session,
+        "preview" => BaseDispos::Preview,
+        other => {
+            return other
+                .parse::<u8>()
+                .map(Disposition::from)
+                .map_err(|_| format!("unknown disposition {other:?}"))
+        }
+    };
+    Ok(Disposition::Base(base))
+}
+
+fn parse_message_id(text: &str) -> CliResult<MessageId> {
+    let raw: [u8; 32] = unhex(text)?
+        .try_into()
+        .map_err(|_| format!("{text:?} is not a 32-byte message id"))?;
+    Ok(MessageId::from_raw_unchecked(raw))
+}
+
+fn parse_expiration(text: &str) -> CliResult<Expiration> {
+    let (kind, time) = text
+        .split_once(' ')
+        .ok_or_else(|| format!("invalid expiration {text:?}"))?;
+    let time = time
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid expiration time {time:?}"))?;
+    match kind {
+        "relative" => Ok(Expiration {
+            relative: true,
+            time,
+        }),
+        "absolute" => Ok(Expiration {
+            relative: false,
+            time,
+        }),
+        _ => Err(format!("invalid expiration kind {kind:?}")),
+    }
+}
+
+fn build(description: &str, out: &str) -> CliResult<()> {
+    let text = std::fs::read_to_string(description)
+        .map_err(|e| format!("cannot read {description}: {e}"))?;
+    let text = text.replace("\r\n", "\n");
+    let (headers, body) = text.split_once("\n\n").unwrap_or((&text, ""));
+
+    let mut builder = MimiContent::builder().salt_with_rng(&mut rand_core::OsRng);
+    let mut content_type = MediaType::TEXT_MARKDOWN_GFM_MIMI.to_string();
+    let mut language = "en".to_string();
+    let mut disposition = Disposition::Base(BaseDispos::Render);
+    let mut topic_id = vec![];
+    let (mut replaces, mut in_reply_to, mut expires) = (None, None, None);
+
+    for line in headers.lines().filter(|line| !line.trim().is_empty()) {
+        let (key, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("invalid header line {line:?}"))?;
+        let value = value.trim();
+        match key.trim() {
+            "sender" => builder = builder.with_sender_uri(value.to_string()),
+            "room" => builder = builder.with_room_uri(value.to_string()),
+            "content-type" => content_type = value.to_string(),
+            "language" => language = value.to_string(),
+            "disposition" => disposition = parse_disposition(value)?,
+            "topic" => topic_id = value.as_bytes().to_vec(),
+            "replaces" => replaces = Some(parse_message_id(value)?),
+            "in-reply-to" => in_reply_to = Some(parse_message_id(value)?),
+            "expires" => expires = Some(parse_expiration(value)?),
+            other => return Err(format!("unknown header {other:?}")),
+        }
+    }
+
+    let mut body = body.trim_end_matches('\n').to_string();
+    if disposition == Disposition::Base(BaseDispos::Reaction) {
+        body = reaction::expand_shortcodes(&body);
+    }
+
+    let mimi_content = builder
+        .topic_id(topic_id.into())
+        .maybe_replaces(replaces)
+        .maybe_in_reply_to(in_reply_to)
+        .maybe_expires(expires)
+        .nested_part(
+            NestedPart::builder()
+                .disposition(disposition)
+                .language(language.into())
+                .part_content(NestedPartContent::SinglePart(SinglePart {
+                    content_type: content_type.into(),
+                    content: body.into_bytes().into(),
+                }))
+                .build(),
+        )
+        .build();
+
+    let bytes = mimi_content.to_cbor_bytes().map_err(|e| e.to_string())?;
+    std::fs::write(out, bytes).map_err(|e| format!("cannot write {out}: {e}"))?;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+
+use crate::{MediaType, MimiContentError, SinglePart, SinglePartRef};
+
+/// Character sets that text parts can be decoded from
+///
+/// MIMI clients SHOULD only send UTF-8, the others are accepted for messages coming
+/// from gateways to legacy systems.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Charset {
+    Utf8,
+    UsAscii,
+    Iso8859_1,
+    Windows1252,
+    /// Big endian unless the content starts with a byte order mark
+    Utf16,
+    Utf16Be,
+    Utf16Le,
+}
+
+/// Code points of windows-1252 bytes 0x80..=0x9F, undefined bytes map to the C1 controls
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+impl Charset {
+    /// Looks up a charset by one of its (case-insensitive) IANA names or aliases
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_ascii_lowercase();
+        Some(match label.as_str() {
+            "utf-8" | "utf8" => Self::Utf8,
+            "us-ascii" | "ascii" | "iso646-us" => Self::UsAscii,
+            "iso-8859-1" | "iso_8859-1" | "latin1" | "l1" => Self::Iso8859_1,
+            "windows-1252" | "cp1252" => Self::Windows1252,
+            "utf-16" => Self::Utf16,
+            "utf-16be" => Self::Utf16Be,
+            "utf-16le" => Self::Utf16Le,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::UsAscii => "us-ascii",
+            Self::Iso8859_1 => "iso-8859-1",
+            Self::Windows1252 => "windows-1252",
+            Self::Utf16 => "utf-16",
+            Self::Utf16Be => "utf-16be",
+            Self::Utf16Le => "utf-16le",
+        }
+    }
+
+    /// Decodes `bytes`, borrowing them when they are valid UTF-8 already
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Result<Cow<'a, str>, MimiContentError> {
+        let invalid = |offset| MimiContentError::InvalidText {
+            charset: *self,
+            offset,
+        };
+        match self {
+            Self::Utf8 => std::str::from_utf8(bytes)
+                .map(Cow::Borrowed)
+                .map_err(|e| invalid(e.valid_up_to())),
+            Self::UsAscii => match bytes.iter().position(|byte| !byte.is_ascii()) {
+                Some(offset) => Err(invalid(offset)),
+                None => Ok(Cow::Borrowed(
+                    std::str::from_utf8(bytes).expect("ASCII is valid UTF-8"),
+                )),
+            },
+            Self::Iso8859_1 => Ok(bytes.iter().map(|&byte| char::from(byte)).collect()),
+            Self::Windows1252 => Ok(bytes
+                .iter()
+                .map(|&byte| match byte {
+                    0x80..=0x9F => WINDOWS_1252_HIGH[usize::from(byte - 0x80)],
+                    _ => char::from(byte),
+                })
+                .collect()),
+            Self::Utf16 => match bytes {
+                [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes, 2, invalid),
+                [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes, 2, invalid),
+                _ => decode_utf16(bytes, u16::from_be_bytes, 0, invalid),
+            },
+            Self::Utf16Be => decode_utf16(bytes, u16::from_be_bytes, 0, invalid),
+            Self::Utf16Le => decode_utf16(bytes, u16::from_le_bytes, 0, invalid),
+        }
+    }
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn decode_utf16(
+    bytes: &[u8],
+    from_bytes: fn([u8; 2]) -> u16,
+    base_offset: usize,
+    invalid: impl Fn(usize) -> MimiContentError,
+) -> Result<Cow<'static, str>, MimiContentError> {
+    let chunks = bytes.chunks_exact(2);
+    let has_remainder = !chunks.remainder().is_empty();
+    let units = chunks.map(|chunk| from_bytes([chunk[0], chunk[1]]));
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut offset = base_offset;
+    for c in char::decode_utf16(units) {
+        let c = c.map_err(|_| invalid(offset))?;
+        text.push(c);
+        offset += c.len_utf16() * 2;
+    }
+    if has_remainder {
+        return Err(invalid(base_offset + bytes.len() - 1));
+    }
+    Ok(Cow::Owned(text))
+}
+
+impl SinglePartRef<'_> {
+    /// The charset of the part, UTF-8 when the content type has no `charset` parameter
+    pub fn charset(&self) -> Result<Charset, MimiContentError> {
+        match MediaType::parse(&self.content_type)?.charset() {
+            None => Ok(Charset::Utf8),
+            Some(label) => Charset::from_label(label)
+                .ok_or_else(|| MimiContentError::UnsupportedCharset(label.to_string())),
+        }
+    }
+
+    /// Decodes the content as text according to its `charset`
+    pub fn text(&self) -> Result<Cow<'_, str>, MimiContentError> {
+        self.charset()?.decode(&self.content)
+    }
+}
+
+impl SinglePart {
+    /// Creates a part holding `text`, encoded as UTF-8 with the matching `charset` parameter
+    pub fn from_text(media_type: MediaType, text: impl Into<String>) -> Self {
+        Self {
+            content_type: media_type.with_parameter("charset", "utf-8").into(),
+            content: text.into().into_bytes().into(),
+        }
+    }
+
+    /// See [`SinglePartRef::charset`]
+    pub fn charset(&self) -> Result<Charset, MimiContentError> {
+        crate::MimiContentAsRef::as_ref(self).charset()
+    }
+
+    /// See [`SinglePartRef::text`]
+    pub fn text(&self) -> Result<Cow<'_, str>, MimiContentError> {
+        self.charset()?.decode(&self.content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Charset;
+    use crate::{MediaType, MimiContentError, SinglePart};
+
+    fn part(content_type: &str, content: &[u8]) -> SinglePart {
+        SinglePart {
+            content_type: content_type.into(),
+            content: content.to_vec().into(),
+        }
+    }
+
+    #[test]
+    fn decodes_charsets() {
+        let cases: [(&str, &[u8], &str); 7] = [
+            ("text/plain", "caf\u{e9}".as_bytes(), "caf\u{e9}"),
+            ("text/plain;charset=US-ASCII", b"cafe", "cafe"),
+            ("text/plain;charset=iso-8859-1", b"caf\xe9", "caf\u{e9}"),
+            (
+                "text/plain;charset=windows-1252",
+                b"\x93hi\x94 \x80",
+                "\u{201C}hi\u{201D} \u{20AC}",
+            ),
+            ("text/plain;charset=utf-16", b"\xff\xfeh\0i\0", "hi"),
+            ("text/plain;charset=utf-16", b"\0h\0i", "hi"),
+            ("text/plain;charset=utf-16le", b"=\xd8\x00\xde", "\u{1F600}"),
+        ];
+        for (content_type, content, expected) in cases {
+            assert_eq!(part(content_type, content).text().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reports_invalid_sequences() {
+        let cases: [(&str, &[u8], Charset, usize); 4] = [
+            ("text/plain", b"ok\xff", Charset::Utf8, 2),
+            ("text/plain;charset=ascii", b"caf\xe9", Charset::UsAscii, 3),
+            (
+                "text/plain;charset=utf-16be",
+                b"\0a\xdc\x00",
+                Charset::Utf16Be,
+                2,
+            ),
+            ("text/plain;charset=utf-16be", b"\0a\0", Charset::Utf16Be, 2),
+        ];
+        for (content_type, content, expected_charset, expected_offset) in cases {
+            match part(content_type, content).text() {
+                Err(MimiContentError::InvalidText { charset, offset }) => {
+                    assert_eq!((charset, offset), (expected_charset, expected_offset));
+                }
+                other => panic!("{content_type}: unexpected {other:?}"),
+            }
+        }
+
+        assert!(matches!(
+            part("text/plain;charset=koi8-r", b"").text(),
+            Err(MimiContentError::UnsupportedCharset(charset)) if charset == "koi8-r"
+        ));
+    }
+
+    #[test]
+    fn constructs_utf8_parts() {
+        let single = SinglePart::from_text(MediaType::TEXT_PLAIN, "gr\u{fc}\u{df}e");
+        assert_eq!(&*single.content_type, "text/plain;charset=utf-8");
+        assert_eq!(single.media_type().unwrap(), MediaType::TEXT_PLAIN_UTF8);
+        assert_eq!(single.text().unwrap(), "gr\u{fc}\u{df}e");
+    }
+}
@@ -0,0 +1,188 @@
+use serde::ser::SerializeSeq as _;
+
+use crate::{MimiContent, MimiContentRef};
+
+impl MimiContentRef<'_> {
+    pub const fn field_count() -> usize {
+        7
+    }
+}
+
+impl serde::Serialize for MimiContentRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut seq =
+            serializer.serialize_seq(Some(Self::field_count() + self.unknown_fields.len()))?;
+        seq.serialize_element(serde_bytes::Bytes::new(self.salt))?;
+        seq.serialize_element(&self.replaces)?;
+        seq.serialize_element(&self.topic_id)?;
+        seq.serialize_element(&self.expires)?;
+        seq.serialize_element(&self.in_reply_to)?;
+        seq.serialize_element(&self.extensions)?;
+        seq.serialize_element(&self.nested_part)?;
+        for unknown_field in self.unknown_fields {
+            seq.serialize_element(unknown_field)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl serde::Serialize for MimiContent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use crate::MimiContentAsRef as _;
+        self.as_ref().serialize(serializer)
+    }
+}
+
+struct MimiContentVisitor;
+impl<'de> serde::de::Visitor<'de> for MimiContentVisitor {
+    type Value = MimiContent;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "a MimiContent struct formatted as a tuple value")
+    }
+
+    fn visit_seq<V>(self, mut seq: V) -> Result<MimiContent, V::Error>
+    where
+        V: serde::de::SeqAccess<'de>,
+    {
+        let salt: serde_bytes::ByteArray<16> = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+        let replaces = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+        let topic_id = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(2, &self))?;
+        let expires = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(3, &self))?;
+        let in_reply_to = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(4, &self))?;
+        let extensions = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(5, &self))?;
+        let nested_part = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(6, &self))?;
+        let unknown_fields =
+            std::iter::from_fn(|| seq.next_element().transpose()).collect::<Result<_, _>>()?;
+
+        Ok(MimiContent {
+            salt: salt.into_array(),
+            replaces,
+            topic_id,
+            expires,
+            in_reply_to,
+            extensions,
+            nested_part,
+            unknown_fields,
+        })
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for MimiContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(MimiContentVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::Value;
+
+    use crate::{
+        MessageId, MimiContent, MimiContentAsRef as _, MimiContentDeserialize as _,
+        MimiContentSerialize as _, NestedPartContent, NestedPartContentCardinality, Tstr,
+    };
+
+    /// Builds the original spec example as it could be sent by a newer client
+    fn future_message() -> Vec<u8> {
+        let mut value: Value =
+            ciborium::from_reader(&include_bytes!("../tests/examples/original.cbor")[..]).unwrap();
+        let fields = value.as_array_mut().unwrap();
+
+        let mut nested_part = fields[6].as_array().unwrap().clone();
+        nested_part.push(Value::Text("new nested part field".into()));
+        let unknown_part = Value::Array(vec![
+            Value::Integer(1.into()),
+            Value::Text("en".into()),
+            Value::Integer(42.into()),
+            Value::Bytes(vec![0xca, 0xfe]),
+            Value::Map(vec![(Value::Integer(1.into()), Value::Bool(true))]),
+        ]);
+        fields[6] = Value::Array(vec![
+            Value::Integer(1.into()),
+            Value::Text("".into()),
+            Value::Integer(3.into()),
+            Value::Integer(2.into()),
+            Value::Array(vec![Value::Array(nested_part), unknown_part]),
+        ]);
+        fields.push(Value::Integer(7.into()));
+        fields.push(Value::Null);
+
+        let mut bytes = vec![];
+        ciborium::into_writer(&value, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn unknown_fields_are_reencoded_verbatim() {
+        let bytes = future_message();
+        let mimi_content = MimiContent::from_cbor_bytes(&bytes).unwrap();
+
+        assert_eq!(mimi_content.unknown_fields.len(), 2);
+        let NestedPartContent::MultiPart(multi) = &mimi_content.nested_part.part_content else {
+            panic!("expected a multipart");
+        };
+        assert_eq!(multi.parts[0].unknown_fields.len(), 1);
+        assert!(matches!(
+            &multi.parts[1].part_content,
+            NestedPartContent::Unknown { cardinality: 42, raw_fields } if raw_fields.len() == 2
+        ));
+        assert_eq!(multi.parts[1].part_content.try_cardinality(), None);
+        assert_eq!(
+            multi.parts[1].part_content.cardinality(),
+            NestedPartContentCardinality::NullPart
+        );
+
+        assert_eq!(mimi_content.to_cbor_bytes().unwrap(), bytes);
+        assert_eq!(mimi_content.as_ref().to_cbor_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn message_id_covers_the_received_bytes() {
+        use digest::Digest as _;
+
+        const SENDER: &str = "mimi://example.com/u/alice-smith";
+        const ROOM: &str = "mimi://example.com/r/engineering_team";
+
+        let bytes = future_message();
+        let mimi_content = MimiContent::from_cbor_bytes(&bytes).unwrap();
+        let message_id = MessageId::construct(
+            Tstr::from(SENDER).as_ref(),
+            Tstr::from(ROOM).as_ref(),
+            &mimi_content,
+        )
+        .unwrap();
+
+        let digest = sha2::Sha256::new()
+            .chain_update(SENDER)
+            .chain_update(ROOM)
+            .chain_update(&bytes)
+            .chain_update(mimi_content.salt)
+            .finalize();
+        assert_eq!(message_id[1..], digest[..31]);
+    }
+}
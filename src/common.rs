@@ -62,8 +62,17 @@ impl std::ops::Deref for TstrRef<'_> {
     }
 }
 
+impl<'a> TstrRef<'a> {
+    /// Unlike [`std::ops::Deref`], the returned string outlives `self`
+    #[inline]
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
 impl MimiContentAsRef for Tstr {
     type Target<'a> = TstrRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> TstrRef {
         TstrRef(self.0.as_str())
     }
@@ -94,6 +103,7 @@ pub struct BstrRef<'a>(#[serde(with = "serde_bytes")] &'a [u8]);
 
 impl MimiContentAsRef for Bstr {
     type Target<'a> = BstrRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> BstrRef {
         BstrRef(&self.0)
     }
@@ -1,4 +1,8 @@
-use crate::{MessageId, MessageIdRef};
+use indexmap::IndexMap;
+
+use crate::{derived::MsgUri, MessageId, MessageIdRef, Timestamp, Value, ValueRef};
+
+mod codec;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -34,6 +38,67 @@ impl From<u8> for MessageStatus {
     }
 }
 
+impl MessageBaseStatus {
+    /// Whether this status ends the progression of a message, i.e. it is neither
+    /// `Unread`, `Delivered` nor `Read`
+    #[inline]
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::Unread | Self::Delivered | Self::Read)
+    }
+
+    /// Precedence used when two statuses are reported for the same message.
+    ///
+    /// Progress statuses advance monotonically (`Unread < Delivered < Read`), and
+    /// terminal statuses override them (`Error < Hidden < Expired < Deleted`)
+    const fn precedence(&self) -> u8 {
+        match self {
+            Self::Unread => 0,
+            Self::Delivered => 1,
+            Self::Read => 2,
+            Self::Error => 3,
+            Self::Hidden => 4,
+            Self::Expired => 5,
+            Self::Deleted => 6,
+        }
+    }
+}
+
+impl MessageStatus {
+    /// Combines two statuses reported for the same message, keeping the one that
+    /// takes precedence.
+    ///
+    /// Extension statuses have no known meaning, so any base status takes precedence over them.
+    /// The operation is commutative, which makes the order in which reports are merged irrelevant.
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (Self::Base(base), Self::Base(other_base)) => {
+                if other_base.precedence() > base.precedence() {
+                    other
+                } else {
+                    self
+                }
+            }
+            (Self::Base(_), Self::Ext(_)) => self,
+            (Self::Ext(_), Self::Base(_)) => other,
+            (Self::Ext(ext), Self::Ext(other_ext)) => Self::Ext(ext.max(other_ext)),
+        }
+    }
+
+    /// Whether the message has at least reached the recipient's client
+    #[inline]
+    pub fn is_delivered(&self) -> bool {
+        matches!(
+            self,
+            Self::Base(MessageBaseStatus::Delivered | MessageBaseStatus::Read)
+        )
+    }
+
+    #[inline]
+    pub fn is_read(&self) -> bool {
+        matches!(self, Self::Base(MessageBaseStatus::Read))
+    }
+}
+
 impl From<MessageStatus> for u8 {
     fn from(value: MessageStatus) -> Self {
         match value {
@@ -43,18 +108,65 @@ impl From<MessageStatus> for u8 {
     }
 }
 
-#[derive(
-    Debug, Clone, PartialEq, Eq, serde_tuple::Serialize_tuple, serde_tuple::Deserialize_tuple,
-)]
+/// Status of a single message.
+///
+/// On the wire, `timestamp` and `extension_data` are optional trailing elements, so reports
+/// that only carry a message id and a status stay byte-identical.
+#[derive(Debug, Clone, PartialEq, Eq, bon::Builder)]
 pub struct PerMessageStatus {
     pub message_id: MessageId,
     pub status: MessageStatus,
+    /// When the status was reached (e.g. the time at which the message was read)
+    pub timestamp: Option<Timestamp>,
+    /// Data attached to the status, usually to give meaning to a [`MessageStatus::Ext`]
+    pub extension_data: Option<Value>,
+}
+
+impl PerMessageStatus {
+    /// Combines two statuses reported for the same message (see [`MessageStatus::merge`]).
+    ///
+    /// The timestamp and extension data follow the status that takes precedence. When both
+    /// statuses are equal, missing values of `self` are filled in from `other`.
+    pub fn merge(self, other: Self) -> Self {
+        let status = self.status.merge(other.status);
+        if status != self.status {
+            return Self {
+                message_id: self.message_id,
+                ..other
+            };
+        }
+
+        if status == other.status {
+            Self {
+                timestamp: self.timestamp.or(other.timestamp),
+                extension_data: self.extension_data.or(other.extension_data),
+                ..self
+            }
+        } else {
+            self
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde_tuple::Serialize_tuple)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PerMessageStatusRef<'a> {
     pub message_id: MessageIdRef<'a>,
     pub status: &'a MessageStatus,
+    pub timestamp: Option<&'a Timestamp>,
+    pub extension_data: Option<ValueRef<'a>>,
+}
+
+impl PerMessageStatusRef<'_> {
+    /// Number of array elements used on the wire
+    pub fn field_count(&self) -> usize {
+        if self.extension_data.is_some() {
+            4
+        } else if self.timestamp.is_some() {
+            3
+        } else {
+            2
+        }
+    }
 }
 
 impl crate::MimiContentAsRef for PerMessageStatus {
@@ -63,6 +175,8 @@ impl crate::MimiContentAsRef for PerMessageStatus {
         PerMessageStatusRef {
             message_id: self.message_id.as_ref(),
             status: &self.status,
+            timestamp: self.timestamp.as_ref(),
+            extension_data: self.extension_data.as_ref().map(Value::as_ref),
         }
     }
 }
@@ -71,13 +185,354 @@ impl crate::MimiContentAsRef for PerMessageStatus {
 #[serde(transparent)]
 pub struct MessageStatusReport(pub Vec<PerMessageStatus>);
 
+#[bon::bon]
+impl MessageStatusReport {
+    /// Builds a report, collapsing duplicate entries for the same message (see [`Self::dedup`])
+    #[builder]
+    pub fn new(#[builder(field)] statuses: Vec<PerMessageStatus>) -> Self {
+        let mut report = Self(statuses);
+        report.dedup();
+        report
+    }
+
+    /// Collapses the entries reported for the same message into a single one, using
+    /// [`PerMessageStatus::merge`]. Messages keep the position of their first occurrence.
+    pub fn dedup(&mut self) {
+        let mut statuses: IndexMap<MessageId, PerMessageStatus> =
+            IndexMap::with_capacity(self.0.len());
+        for per_message_status in self.0.drain(..) {
+            match statuses.entry(per_message_status.message_id) {
+                indexmap::map::Entry::Occupied(mut entry) => {
+                    let merged = entry.get().clone().merge(per_message_status);
+                    entry.insert(merged);
+                }
+                indexmap::map::Entry::Vacant(entry) => {
+                    entry.insert(per_message_status);
+                }
+            }
+        }
+
+        self.0 = statuses.into_values().collect();
+    }
+
+    /// Records a status, merging it with any status already present for the same message
+    pub fn merge_status(&mut self, per_message_status: PerMessageStatus) {
+        match self
+            .0
+            .iter_mut()
+            .find(|existing| existing.message_id == per_message_status.message_id)
+        {
+            Some(existing) => *existing = existing.clone().merge(per_message_status),
+            None => self.0.push(per_message_status),
+        }
+    }
+
+    /// Merges every status of `other` into `self`
+    pub fn merge(&mut self, other: &Self) {
+        for per_message_status in &other.0 {
+            self.merge_status(per_message_status.clone());
+        }
+    }
+
+    pub fn status_of(&self, message_id: &MessageId) -> Option<MessageStatus> {
+        self.0
+            .iter()
+            .find(|per_message_status| per_message_status.message_id == *message_id)
+            .map(|per_message_status| per_message_status.status)
+    }
+}
+
+impl<S: message_status_report_builder::State> MessageStatusReportBuilder<S> {
+    pub fn with_status(self, message_id: MessageId, status: MessageStatus) -> Self {
+        self.with_per_message_status(
+            PerMessageStatus::builder()
+                .message_id(message_id)
+                .status(status)
+                .build(),
+        )
+    }
+
+    pub fn with_per_message_status(mut self, per_message_status: PerMessageStatus) -> Self {
+        self.statuses.push(per_message_status);
+        self
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
 #[serde(transparent)]
-pub struct MessageStatusReportRef<'a>(Vec<PerMessageStatusRef<'a>>);
+pub struct MessageStatusReportRef<'a>(&'a [PerMessageStatus]);
 
 impl crate::MimiContentAsRef for MessageStatusReport {
     type Target<'a> = MessageStatusReportRef<'a>;
     fn as_ref(&self) -> Self::Target<'_> {
-        MessageStatusReportRef(self.0.iter().map(PerMessageStatus::as_ref).collect())
+        MessageStatusReportRef(&self.0)
+    }
+}
+
+/// Delivery summary of a single message across the members of a room
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MessageStatusSummary {
+    /// Number of recipients the message was sent to
+    pub recipients: usize,
+    /// Recipients whose client received the message (this includes readers)
+    pub delivered: usize,
+    /// Recipients who read the message
+    pub read: usize,
+    /// Recipients that reported an `Error` status
+    pub errored: usize,
+}
+
+impl MessageStatusSummary {
+    #[inline]
+    pub fn delivered_to_all(&self) -> bool {
+        self.recipients > 0 && self.delivered >= self.recipients
+    }
+
+    #[inline]
+    pub fn read_by_all(&self) -> bool {
+        self.recipients > 0 && self.read >= self.recipients
+    }
+}
+
+/// Aggregates the status reports sent by each member of a room
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RoomStatusAggregate {
+    per_recipient: IndexMap<MsgUri, MessageStatusReport>,
+}
+
+impl RoomStatusAggregate {
+    /// Merges a report sent by `recipient` (usually the `sender_user_url` of the report's
+    /// [`crate::derived::MessageDerivedValues`]) into the aggregate
+    pub fn add_report(&mut self, recipient: MsgUri, report: &MessageStatusReport) {
+        self.per_recipient
+            .entry(recipient)
+            .or_insert_with(|| MessageStatusReport(vec![]))
+            .merge(report);
+    }
+
+    pub fn status_for(&self, recipient: &MsgUri, message_id: &MessageId) -> Option<MessageStatus> {
+        self.per_recipient
+            .get(recipient)
+            .and_then(|report| report.status_of(message_id))
+    }
+
+    /// Summarizes the status of `message_id` among `recipients` members.
+    ///
+    /// Recipients that haven't reported anything for this message count as undelivered.
+    pub fn summary(&self, message_id: &MessageId, recipients: usize) -> MessageStatusSummary {
+        self.per_recipient
+            .values()
+            .filter_map(|report| report.status_of(message_id))
+            .fold(
+                MessageStatusSummary {
+                    recipients,
+                    ..Default::default()
+                },
+                |mut summary, status| {
+                    summary.delivered += usize::from(status.is_delivered());
+                    summary.read += usize::from(status.is_read());
+                    summary.errored +=
+                        usize::from(status == MessageStatus::Base(MessageBaseStatus::Error));
+                    summary
+                },
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        derived::MsgUri, MessageId, MimiContentDeserialize as _, MimiContentSerialize as _,
+        Timestamp,
+    };
+
+    use super::{
+        MessageBaseStatus, MessageStatus, MessageStatusReport, PerMessageStatus,
+        RoomStatusAggregate,
+    };
+
+    const UNREAD: MessageStatus = MessageStatus::Base(MessageBaseStatus::Unread);
+    const DELIVERED: MessageStatus = MessageStatus::Base(MessageBaseStatus::Delivered);
+    const READ: MessageStatus = MessageStatus::Base(MessageBaseStatus::Read);
+    const DELETED: MessageStatus = MessageStatus::Base(MessageBaseStatus::Deleted);
+    const ERROR: MessageStatus = MessageStatus::Base(MessageBaseStatus::Error);
+
+    fn message_id(n: u8) -> MessageId {
+        let mut raw = [n; 32];
+        raw[0] = 0x01;
+        MessageId::from_raw_unchecked(raw)
+    }
+
+    #[test]
+    fn status_merge_follows_precedence() {
+        assert_eq!(READ.merge(DELIVERED), READ);
+        assert_eq!(UNREAD.merge(DELIVERED), DELIVERED);
+        assert_eq!(READ.merge(DELETED), DELETED);
+        assert_eq!(DELETED.merge(ERROR), DELETED);
+        assert_eq!(MessageStatus::Ext(42).merge(UNREAD), UNREAD);
+
+        for a in 0..=8u8 {
+            for b in 0..=8u8 {
+                let (a, b) = (MessageStatus::from(a), MessageStatus::from(b));
+                assert_eq!(a.merge(b), b.merge(a));
+            }
+        }
+    }
+
+    #[test]
+    fn builder_dedups_statuses() {
+        let report = MessageStatusReport::builder()
+            .with_status(message_id(1), DELIVERED)
+            .with_status(message_id(2), UNREAD)
+            .with_status(message_id(1), READ)
+            .with_status(message_id(1), DELIVERED)
+            .build();
+
+        assert_eq!(
+            report.0,
+            vec![
+                PerMessageStatus::builder()
+                    .message_id(message_id(1))
+                    .status(READ)
+                    .build(),
+                PerMessageStatus::builder()
+                    .message_id(message_id(2))
+                    .status(UNREAD)
+                    .build(),
+            ]
+        );
+    }
+
+    #[test]
+    fn aggregate_summarizes_room() {
+        let mut aggregate = RoomStatusAggregate::default();
+        let bob = MsgUri::from("mimi://example.com/u/bob");
+        let cathy = MsgUri::from("mimi://example.com/u/cathy");
+
+        aggregate.add_report(
+            bob.clone(),
+            &MessageStatusReport::builder()
+                .with_status(message_id(1), DELIVERED)
+                .build(),
+        );
+        aggregate.add_report(
+            cathy.clone(),
+            &MessageStatusReport::builder()
+                .with_status(message_id(1), READ)
+                .with_status(message_id(2), ERROR)
+                .build(),
+        );
+        aggregate.add_report(
+            bob.clone(),
+            &MessageStatusReport::builder()
+                .with_status(message_id(1), UNREAD)
+                .build(),
+        );
+
+        assert_eq!(aggregate.status_for(&bob, &message_id(1)), Some(DELIVERED));
+
+        let summary = aggregate.summary(&message_id(1), 2);
+        assert!(summary.delivered_to_all());
+        assert!(!summary.read_by_all());
+        assert_eq!(summary.read, 1);
+
+        let summary = aggregate.summary(&message_id(2), 3);
+        assert!(!summary.delivered_to_all());
+        assert_eq!(summary.errored, 1);
+    }
+
+    #[test]
+    fn merge_keeps_timestamp_of_winning_status() {
+        let delivered = PerMessageStatus::builder()
+            .message_id(message_id(1))
+            .status(DELIVERED)
+            .timestamp(Timestamp::MsecsSinceEpoch(1000))
+            .build();
+        let read = PerMessageStatus::builder()
+            .message_id(message_id(1))
+            .status(READ)
+            .timestamp(Timestamp::MsecsSinceEpoch(2000))
+            .build();
+        let read_without_timestamp = PerMessageStatus::builder()
+            .message_id(message_id(1))
+            .status(READ)
+            .build();
+
+        assert_eq!(delivered.clone().merge(read.clone()), read);
+        assert_eq!(read.clone().merge(delivered), read);
+        assert_eq!(read_without_timestamp.merge(read.clone()), read);
+    }
+
+    #[test]
+    fn optional_trailing_fields_roundtrip() {
+        let report = MessageStatusReport::builder()
+            .with_status(message_id(1), DELIVERED)
+            .with_per_message_status(
+                PerMessageStatus::builder()
+                    .message_id(message_id(2))
+                    .status(READ)
+                    .timestamp(Timestamp::MsecsSinceEpoch(1644387225019))
+                    .build(),
+            )
+            .with_per_message_status(
+                PerMessageStatus::builder()
+                    .message_id(message_id(3))
+                    .status(MessageStatus::Ext(42))
+                    .extension_data(ciborium::Value::Text("reason".into()).into())
+                    .build(),
+            )
+            .build();
+
+        let bytes = report.to_cbor_bytes().unwrap();
+        let value: ciborium::Value = ciborium::from_reader(&bytes[..]).unwrap();
+        let lengths: Vec<usize> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.as_array().unwrap().len())
+            .collect();
+        assert_eq!(lengths, vec![2, 3, 4]);
+        assert_eq!(
+            MessageStatusReport::from_cbor_bytes(&bytes).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn ignores_fields_from_newer_versions() {
+        let report = MessageStatusReport::builder()
+            .with_per_message_status(
+                PerMessageStatus::builder()
+                    .message_id(message_id(1))
+                    .status(READ)
+                    .timestamp(Timestamp::MsecsSinceEpoch(1644387225019))
+                    .build(),
+            )
+            .with_status(message_id(2), DELIVERED)
+            .build();
+
+        let mut value: ciborium::Value =
+            ciborium::from_reader(&report.to_cbor_bytes().unwrap()[..]).unwrap();
+        let entry = value.as_array_mut().unwrap()[0].as_array_mut().unwrap();
+        entry.push(ciborium::Value::Null);
+        entry.push(ciborium::Value::Integer(7.into()));
+        let mut bytes = vec![];
+        ciborium::into_writer(&value, &mut bytes).unwrap();
+
+        assert_eq!(
+            MessageStatusReport::from_cbor_bytes(&bytes).unwrap(),
+            report
+        );
+    }
+
+    #[test]
+    fn spec_report_has_no_trailing_fields() {
+        let report =
+            MessageStatusReport::from_cbor_bytes(include_bytes!("../tests/examples/report.cbor"))
+                .unwrap();
+        assert!(report
+            .0
+            .iter()
+            .all(|status| status.timestamp.is_none() && status.extension_data.is_none()));
     }
 }
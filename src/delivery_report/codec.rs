@@ -0,0 +1,80 @@
+use serde::ser::SerializeSeq as _;
+
+use crate::{
+    delivery_report::{PerMessageStatus, PerMessageStatusRef},
+    Timestamp,
+};
+
+impl serde::Serialize for PerMessageStatusRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let field_count = self.field_count();
+        let mut seq = serializer.serialize_seq(Some(field_count))?;
+        seq.serialize_element(&self.message_id)?;
+        seq.serialize_element(&self.status)?;
+        if field_count > 2 {
+            seq.serialize_element(&self.timestamp)?;
+        }
+        if let Some(extension_data) = &self.extension_data {
+            seq.serialize_element(extension_data)?;
+        }
+
+        seq.end()
+    }
+}
+
+impl serde::Serialize for PerMessageStatus {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use crate::MimiContentAsRef as _;
+        self.as_ref().serialize(serializer)
+    }
+}
+
+struct PerMessageStatusVisitor;
+impl<'de> serde::de::Visitor<'de> for PerMessageStatusVisitor {
+    type Value = PerMessageStatus;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "a PerMessageStatus struct formatted as a tuple value"
+        )
+    }
+
+    fn visit_seq<V>(self, mut seq: V) -> Result<PerMessageStatus, V::Error>
+    where
+        V: serde::de::SeqAccess<'de>,
+    {
+        let message_id = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+        let status = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+        let timestamp = seq.next_element::<Option<Timestamp>>()?.flatten();
+        let extension_data = seq.next_element()?.flatten();
+        // fields added by newer versions
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+
+        Ok(PerMessageStatus {
+            message_id,
+            status,
+            timestamp,
+            extension_data,
+        })
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for PerMessageStatus {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(PerMessageStatusVisitor)
+    }
+}
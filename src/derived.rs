@@ -1,4 +1,7 @@
-use crate::{Bstr, BstrRef, MessageId, MessageIdRef, Tstr, TstrRef};
+use crate::{
+    Bstr, BstrRef, Expiration, MessageId, MessageIdRef, MimiContent, MimiContentAsRef as _,
+    MimiContentError, Timestamp, Tstr, TstrRef,
+};
 
 pub type MsgUri = Tstr;
 pub type MsgUriRef<'a> = TstrRef<'a>;
@@ -39,3 +42,238 @@ impl crate::MimiContentAsRef for MessageDerivedValues {
         }
     }
 }
+
+/// Bounds used to decide whether a hub-accepted timestamp is plausible
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimestampPlausibility {
+    /// The receiver's current time, in milliseconds since the UNIX epoch
+    pub now_msecs: u64,
+    /// How far ahead of `now_msecs` the hub's clock is allowed to be
+    pub max_clock_skew_msecs: u64,
+}
+
+/// A single disagreement between a [`MimiContent`] and the [`MessageDerivedValues`] of the hub
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DerivedValuesMismatch {
+    /// The message id doesn't match the one recomputed from the content, sender and room
+    MessageId {
+        derived: MessageId,
+        recomputed: MessageId,
+    },
+    /// The message id uses a hash algorithm that cannot be recomputed locally
+    UnsupportedMessageIdHashAlg(u8),
+    /// The sender URI extension of the content differs from the sender user URL
+    SenderUri { content: Tstr, derived: MsgUri },
+    /// The room URI extension of the content differs from the room URL
+    RoomUri { content: Tstr, derived: MsgUri },
+    /// The hub accepted the message at a time that is still in the future
+    TimestampInFuture {
+        timestamp_msecs: u64,
+        now_msecs: u64,
+    },
+    /// The hub accepted the message after its absolute expiration time
+    AcceptedAfterExpiry {
+        timestamp_msecs: u64,
+        expires_secs: u32,
+    },
+}
+
+/// Outcome of [`MessageDerivedValues::check_consistency`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    pub mismatches: Vec<DerivedValuesMismatch>,
+}
+
+impl ConsistencyReport {
+    #[inline]
+    pub fn is_consistent(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl MessageDerivedValues {
+    /// Checks that `mimi_content` agrees with the values derived by the hub.
+    ///
+    /// The following checks are performed:
+    /// - The message id is recomputed from the sender user URL, the room URL and the content
+    /// - The sender and room URI extensions, when present, match the derived URLs
+    /// - The hub-accepted timestamp is not in the future and not past an absolute expiration
+    ///
+    /// Timestamps using the RFC9581 extended time format are not checked.
+    pub fn check_consistency(
+        &self,
+        mimi_content: &MimiContent,
+        timestamp_plausibility: TimestampPlausibility,
+    ) -> Result<ConsistencyReport, MimiContentError> {
+        let mut mismatches = vec![];
+
+        match self.message_id.as_ref().hash_alg() {
+            0x01 => {
+                let recomputed = MessageId::construct(
+                    self.sender_user_url.as_ref(),
+                    self.room_url.as_ref(),
+                    mimi_content,
+                )?;
+                if recomputed != self.message_id {
+                    mismatches.push(DerivedValuesMismatch::MessageId {
+                        derived: self.message_id,
+                        recomputed,
+                    });
+                }
+            }
+            hash_alg => {
+                mismatches.push(DerivedValuesMismatch::UnsupportedMessageIdHashAlg(hash_alg))
+            }
+        }
+
+        if let Some(sender_uri) = mimi_content.get_sender_uri() {
+            if *sender_uri != self.sender_user_url {
+                mismatches.push(DerivedValuesMismatch::SenderUri {
+                    content: (*sender_uri).clone(),
+                    derived: self.sender_user_url.clone(),
+                });
+            }
+        }
+
+        if let Some(room_uri) = mimi_content.get_room_uri() {
+            if *room_uri != self.room_url {
+                mismatches.push(DerivedValuesMismatch::RoomUri {
+                    content: (*room_uri).clone(),
+                    derived: self.room_url.clone(),
+                });
+            }
+        }
+
+        if let Timestamp::MsecsSinceEpoch(timestamp_msecs) = self.hub_accepted_timestamp {
+            let TimestampPlausibility {
+                now_msecs,
+                max_clock_skew_msecs,
+            } = timestamp_plausibility;
+
+            if timestamp_msecs > now_msecs.saturating_add(max_clock_skew_msecs) {
+                mismatches.push(DerivedValuesMismatch::TimestampInFuture {
+                    timestamp_msecs,
+                    now_msecs,
+                });
+            }
+
+            if let Some(Expiration {
+                relative: false,
+                time: expires_secs,
+            }) = mimi_content.expires
+            {
+                if timestamp_msecs / 1000 > u64::from(expires_secs) {
+                    mismatches.push(DerivedValuesMismatch::AcceptedAfterExpiry {
+                        timestamp_msecs,
+                        expires_secs,
+                    });
+                }
+            }
+        }
+
+        Ok(ConsistencyReport { mismatches })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        MessageId, MimiContent, MimiContentAsRef as _, MimiContentDeserialize as _, NestedPart,
+        Timestamp, Tstr,
+    };
+
+    use super::{DerivedValuesMismatch, MessageDerivedValues, TimestampPlausibility};
+
+    const SENDER: &str = "mimi://example.com/u/alice-smith";
+    const ROOM: &str = "mimi://example.com/r/engineering_team";
+    const ACCEPTED_AT: u64 = 1644387225019;
+
+    fn plausibility() -> TimestampPlausibility {
+        TimestampPlausibility {
+            now_msecs: ACCEPTED_AT + 1000,
+            max_clock_skew_msecs: 0,
+        }
+    }
+
+    fn derived_values_for(mimi_content: &MimiContent) -> MessageDerivedValues {
+        MessageDerivedValues {
+            message_id: MessageId::construct(
+                Tstr::from(SENDER).as_ref(),
+                Tstr::from(ROOM).as_ref(),
+                mimi_content,
+            )
+            .unwrap(),
+            hub_accepted_timestamp: Timestamp::MsecsSinceEpoch(ACCEPTED_AT),
+            mls_group_id: vec![0xee; 32].into(),
+            sender_leaf_index: 4,
+            sender_client_url: "mimi://example.com/d/alice-phone".into(),
+            sender_user_url: SENDER.into(),
+            room_url: ROOM.into(),
+        }
+    }
+
+    #[test]
+    fn consistent_values_pass() {
+        let mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy([7; 16])
+            .topic_id(vec![].into())
+            .nested_part(NestedPart::default())
+            .with_sender_uri(SENDER.into())
+            .with_room_uri(ROOM.into())
+            .build();
+
+        let report = derived_values_for(&mimi_content)
+            .check_consistency(&mimi_content, plausibility())
+            .unwrap();
+        assert!(report.is_consistent(), "{report:?}");
+    }
+
+    #[test]
+    fn spoofed_sender_is_reported() {
+        let mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy([7; 16])
+            .topic_id(vec![].into())
+            .nested_part(NestedPart::default())
+            .with_sender_uri("mimi://example.com/u/mallory".into())
+            .build();
+
+        let mut derived_values = derived_values_for(&mimi_content);
+        derived_values.hub_accepted_timestamp = Timestamp::MsecsSinceEpoch(ACCEPTED_AT + 5000);
+
+        let report = derived_values
+            .check_consistency(&mimi_content, plausibility())
+            .unwrap();
+        assert_eq!(
+            report.mismatches,
+            vec![
+                DerivedValuesMismatch::SenderUri {
+                    content: "mimi://example.com/u/mallory".into(),
+                    derived: SENDER.into(),
+                },
+                DerivedValuesMismatch::TimestampInFuture {
+                    timestamp_msecs: ACCEPTED_AT + 5000,
+                    now_msecs: ACCEPTED_AT + 1000,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn tampered_content_is_reported() {
+        let mimi_content =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/original.cbor"))
+                .unwrap();
+        let derived_values = derived_values_for(&mimi_content);
+
+        let mut tampered = mimi_content.clone();
+        tampered.in_reply_to = Some(derived_values.message_id);
+
+        let report = derived_values
+            .check_consistency(&tampered, plausibility())
+            .unwrap();
+        assert!(matches!(
+            report.mismatches.as_slice(),
+            [DerivedValuesMismatch::MessageId { .. }]
+        ));
+    }
+}
@@ -0,0 +1,414 @@
+//! Structural comparison of two versions of a message, e.g. to show the history of edits
+//! made with `replaces`
+
+use crate::{MimiContent, MultiPart, NestedPart, NestedPartContent, SinglePart};
+
+/// A difference between two nested part trees, at a path of indices through the `parts`
+/// of each multipart (see [`crate::PartTree::get`]). The path is into the new tree, except
+/// for [`Self::Removed`] parts which are only in the old one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartChange<'a> {
+    Added {
+        path: Vec<usize>,
+        part: &'a NestedPart,
+    },
+    Removed {
+        path: Vec<usize>,
+        part: &'a NestedPart,
+    },
+    /// The part differs, ignoring the parts of a multipart (which are compared separately)
+    Changed {
+        path: Vec<usize>,
+        old: &'a NestedPart,
+        new: &'a NestedPart,
+        /// Word-level diff, when both versions are decodable text parts
+        text_diff: Option<Vec<WordChange>>,
+    },
+}
+
+impl PartChange<'_> {
+    pub fn path(&self) -> &[usize] {
+        match self {
+            Self::Added { path, .. } | Self::Removed { path, .. } | Self::Changed { path, .. } => {
+                path
+            }
+        }
+    }
+}
+
+/// A run of words (including the whitespace between them) in a text diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordChange {
+    Unchanged(String),
+    Added(String),
+    Removed(String),
+}
+
+/// Compares two nested part trees, returning the changes in depth-first order.
+///
+/// The parts of multiparts are matched before being compared, so inserting a part only
+/// reports that part as added.
+pub fn diff_parts<'a>(old: &'a NestedPart, new: &'a NestedPart) -> Vec<PartChange<'a>> {
+    let mut changes = vec![];
+    diff_into(old, new, &mut vec![], &mut vec![], &mut changes);
+    changes
+}
+
+fn children(part: &NestedPart) -> Option<&MultiPart> {
+    match &part.part_content {
+        NestedPartContent::MultiPart(multi) => Some(multi),
+        _ => None,
+    }
+}
+
+fn diff_into<'a>(
+    old: &'a NestedPart,
+    new: &'a NestedPart,
+    old_path: &mut Vec<usize>,
+    new_path: &mut Vec<usize>,
+    changes: &mut Vec<PartChange<'a>>,
+) {
+    let (Some(old_multi), Some(new_multi)) = (children(old), children(new)) else {
+        if old != new {
+            changes.push(PartChange::Changed {
+                path: new_path.clone(),
+                old,
+                new,
+                text_diff: text_diff(&old.part_content, &new.part_content),
+            });
+        }
+        return;
+    };
+
+    if old.disposition != new.disposition
+        || old.language != new.language
+        || old.unknown_fields != new.unknown_fields
+        || old_multi.part_semantics != new_multi.part_semantics
+    {
+        changes.push(PartChange::Changed {
+            path: new_path.clone(),
+            old,
+            new,
+            text_diff: None,
+        });
+    }
+
+    // Parts left between two identical ones are compared pairwise, the extra ones are
+    // removed or added
+    let (mut removed, mut added) = (vec![], vec![]);
+    let mut flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, changes: &mut Vec<_>| {
+        for (&i, &j) in removed.iter().zip(added.iter()) {
+            old_path.push(i);
+            new_path.push(j);
+            diff_into(
+                &old_multi.parts[i],
+                &new_multi.parts[j],
+                old_path,
+                new_path,
+                changes,
+            );
+            old_path.pop();
+            new_path.pop();
+        }
+        let paired = removed.len().min(added.len());
+        for &i in &removed[paired..] {
+            changes.push(PartChange::Removed {
+                path: [old_path.as_slice(), &[i]].concat(),
+                part: &old_multi.parts[i],
+            });
+        }
+        for &j in &added[paired..] {
+            changes.push(PartChange::Added {
+                path: [new_path.as_slice(), &[j]].concat(),
+                part: &new_multi.parts[j],
+            });
+        }
+        removed.clear();
+        added.clear();
+    };
+    for edit in align(&old_multi.parts, &new_multi.parts) {
+        match edit {
+            Edit::Keep(..) => flush(&mut removed, &mut added, changes),
+            Edit::Remove(i) => removed.push(i),
+            Edit::Add(j) => added.push(j),
+        }
+    }
+    flush(&mut removed, &mut added, changes);
+}
+
+/// A step turning one sequence into another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep(usize, usize),
+    Remove(usize),
+    Add(usize),
+}
+
+/// Above this many cells, the table of the longest common subsequence isn't computed and the
+/// differing middles of the sequences are replaced wholesale
+const MAX_LCS_CELLS: usize = 1 << 20;
+
+/// Aligns two sequences on their longest common subsequence, after stripping their common
+/// prefix and suffix
+fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Keep(i, i)).collect();
+    let cells = (old_middle.len() + 1).saturating_mul(new_middle.len() + 1);
+    if cells > MAX_LCS_CELLS {
+        edits.extend((0..old_middle.len()).map(|i| Edit::Remove(prefix + i)));
+        edits.extend((0..new_middle.len()).map(|j| Edit::Add(prefix + j)));
+    } else {
+        let (n, m) = (old_middle.len(), new_middle.len());
+        // lcs[i * (m + 1) + j] is the length of the longest common subsequence of
+        // old_middle[i..] and new_middle[j..]
+        let mut lcs = vec![0u32; cells];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[i * (m + 1) + j] = if old_middle[i] == new_middle[j] {
+                    lcs[(i + 1) * (m + 1) + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && old_middle[i] == new_middle[j] {
+                edits.push(Edit::Keep(prefix + i, prefix + j));
+                (i, j) = (i + 1, j + 1);
+            } else if i < n && (j == m || lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1]) {
+                edits.push(Edit::Remove(prefix + i));
+                i += 1;
+            } else {
+                edits.push(Edit::Add(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    let (old_suffix, new_suffix) = (old.len() - suffix, new.len() - suffix);
+    edits.extend((0..suffix).map(|k| Edit::Keep(old_suffix + k, new_suffix + k)));
+    edits
+}
+
+fn decoded_text(single: &SinglePart) -> Option<String> {
+    if !single.media_type().ok()?.is_text() {
+        return None;
+    }
+    single.text().ok().map(Into::into)
+}
+
+fn text_diff(old: &NestedPartContent, new: &NestedPartContent) -> Option<Vec<WordChange>> {
+    let (NestedPartContent::SinglePart(old), NestedPartContent::SinglePart(new)) = (old, new)
+    else {
+        return None;
+    };
+    Some(diff_words(&decoded_text(old)?, &decoded_text(new)?))
+}
+
+/// Splits `text` into runs of whitespace and runs of anything else
+fn words(text: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let is_whitespace = c.is_whitespace();
+        if let Some(&(next_start, next)) = chars.peek() {
+            if next.is_whitespace() != is_whitespace {
+                words.push(&text[start..next_start]);
+                start = next_start;
+            }
+        }
+    }
+    if start < text.len() {
+        words.push(&text[start..]);
+    }
+    words
+}
+
+/// Computes a word-level diff, as the longest common subsequence of words.
+///
+/// When the differing middles of both texts are too long to compare, they are reported as
+/// removed and added as a whole.
+pub fn diff_words(old: &str, new: &str) -> Vec<WordChange> {
+    let (old, new) = (words(old), words(new));
+
+    let mut changes: Vec<WordChange> = vec![];
+    let mut push = |change: WordChange| match (changes.last_mut(), change) {
+        (Some(WordChange::Unchanged(last)), WordChange::Unchanged(word))
+        | (Some(WordChange::Added(last)), WordChange::Added(word))
+        | (Some(WordChange::Removed(last)), WordChange::Removed(word)) => last.push_str(&word),
+        (_, change) => changes.push(change),
+    };
+    for edit in align(&old, &new) {
+        push(match edit {
+            Edit::Keep(i, _) => WordChange::Unchanged(old[i].to_string()),
+            Edit::Remove(i) => WordChange::Removed(old[i].to_string()),
+            Edit::Add(j) => WordChange::Added(new[j].to_string()),
+        });
+    }
+    changes
+}
+
+impl MimiContent {
+    /// Compares the body of `self` with the body of a `newer` version, such as an edit
+    /// that `replaces` it
+    pub fn diff<'a>(&'a self, newer: &'a Self) -> Vec<PartChange<'a>> {
+        diff_parts(&self.nested_part, &newer.nested_part)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_words, PartChange, WordChange};
+    use crate::{
+        MimiContent, MimiContentDeserialize as _, MultiPart, NestedPart, NestedPartContent,
+        PartSemantics,
+    };
+
+    #[test]
+    fn diffs_words() {
+        use WordChange::*;
+        assert_eq!(
+            diff_words("we shipped release 2.0", "we just shipped release 2.1"),
+            vec![
+                Unchanged("we ".into()),
+                Added("just ".into()),
+                Unchanged("shipped release ".into()),
+                Removed("2.0".into()),
+                Added("2.1".into()),
+            ]
+        );
+        assert_eq!(diff_words("", ""), vec![]);
+        assert_eq!(diff_words("", "hi"), vec![Added("hi".into())]);
+    }
+
+    #[test]
+    fn diffs_spec_edit() {
+        let original =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/original.cbor"))
+                .unwrap();
+        let edit =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/edit.cbor")).unwrap();
+
+        let changes = original.diff(&edit);
+        let [PartChange::Changed {
+            path,
+            text_diff: Some(text_diff),
+            ..
+        }] = changes.as_slice()
+        else {
+            panic!("unexpected changes {changes:?}");
+        };
+        assert!(path.is_empty());
+        assert!(text_diff
+            .iter()
+            .any(|change| matches!(change, WordChange::Removed(_) | WordChange::Added(_))));
+        assert!(original.diff(&original).is_empty());
+    }
+
+    #[test]
+    fn diffs_multiparts() {
+        let multipart = |parts: Vec<NestedPart>| NestedPart {
+            part_content: NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts,
+            }),
+            ..Default::default()
+        };
+        let language = |language: &str| NestedPart {
+            language: language.into(),
+            ..Default::default()
+        };
+
+        let old = multipart(vec![language("en"), language("fr")]);
+        let new = multipart(vec![language("en"), language("de"), language("it")]);
+        let changes = super::diff_parts(&old, &new);
+        assert_eq!(changes.len(), 2);
+        assert!(
+            matches!(&changes[0], PartChange::Changed { path, text_diff: None, .. } if path == &[1])
+        );
+        assert!(
+            matches!(&changes[1], PartChange::Added { path, part } if path == &[2] && &*part.language == "it")
+        );
+
+        let removed = super::diff_parts(&new, &old);
+        assert!(matches!(removed.last(), Some(PartChange::Removed { path, .. }) if path == &[2]));
+    }
+
+    #[test]
+    fn matches_parts_before_diffing_them() {
+        let multipart = |parts: Vec<NestedPart>| NestedPart {
+            part_content: NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts,
+            }),
+            ..Default::default()
+        };
+        let language = |language: &str| NestedPart {
+            language: language.into(),
+            ..Default::default()
+        };
+
+        let old = multipart(vec![language("en"), language("fr"), language("de")]);
+        let new = multipart(vec![
+            language("it"),
+            language("en"),
+            language("fr"),
+            language("es"),
+        ]);
+        let changes = super::diff_parts(&old, &new);
+        assert_eq!(changes.len(), 2, "{changes:?}");
+        assert!(
+            matches!(&changes[0], PartChange::Added { path, part } if path == &[0] && &*part.language == "it")
+        );
+        assert!(matches!(
+            &changes[1],
+            PartChange::Changed { path, old, new, .. }
+                if path == &[3] && &*old.language == "de" && &*new.language == "es"
+        ));
+
+        let removed = super::diff_parts(&new, &old);
+        assert!(matches!(&removed[0], PartChange::Removed { path, .. } if path == &[0]));
+    }
+
+    #[test]
+    fn bounds_the_cost_of_long_texts() {
+        use WordChange::*;
+        let old = "a ".repeat(50_000);
+        let new = format!("start {}", "b ".repeat(50_000));
+        let changes = diff_words(&old, &new);
+        assert_eq!(
+            changes,
+            vec![
+                Removed(old.trim_end().to_string()),
+                Added(new.trim_end().to_string()),
+                Unchanged(" ".into()),
+            ]
+        );
+
+        // long common prefixes and suffixes don't count towards the limit
+        let old = format!("{}old{}", "a ".repeat(50_000), " z".repeat(50_000));
+        let new = format!("{}new{}", "a ".repeat(50_000), " z".repeat(50_000));
+        let changes = diff_words(&old, &new);
+        assert!(matches!(
+            changes.as_slice(),
+            [Unchanged(_), Removed(removed), Added(added), Unchanged(_)]
+                if removed == "old" && added == "new"
+        ));
+    }
+}
@@ -0,0 +1,525 @@
+//! CBOR extended diagnostic notation (EDN, [RFC8949 Section 8](https://www.rfc-editor.org/rfc/rfc8949.html#name-diagnostic-notation))
+//!
+//! Any wire type of this crate can be rendered to EDN through [`MimiContentEdn`] and parsed
+//! back through [`MimiContentFromEdn`], which allows fixtures to be authored and reviewed as text.
+//!
+//! The parser understands the subset of EDN needed to describe MIMI messages: integers,
+//! floats (including `NaN` and `Infinity`), text strings, `h'...'` and `b64'...'` byte
+//! strings, arrays, maps, tags, `true`, `false`, `null` and `/ ... /` comments.
+
+use ciborium::Value;
+
+use crate::{MimiContentDeserialize, MimiContentError, MimiContentSerialize};
+
+/// Renders `value` on a single line
+pub fn to_edn(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, None);
+    out
+}
+
+/// Renders `value` with one nested container per line, indented by two spaces
+pub fn to_edn_pretty(value: &Value) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, Some(0));
+    out
+}
+
+/// Parses a single EDN value
+pub fn from_edn(text: &str) -> Result<Value, MimiContentError> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace()?;
+    if parser.pos != text.len() {
+        return Err(parser.error("trailing characters after value"));
+    }
+    Ok(value)
+}
+
+pub trait MimiContentEdn: MimiContentSerialize {
+    fn to_edn(&self) -> Result<String, MimiContentError> {
+        Ok(to_edn(&Value::serialized(self)?))
+    }
+
+    fn to_edn_pretty(&self) -> Result<String, MimiContentError> {
+        Ok(to_edn_pretty(&Value::serialized(self)?))
+    }
+}
+
+pub trait MimiContentFromEdn: MimiContentDeserialize {
+    fn from_edn(text: &str) -> Result<Self, MimiContentError> {
+        Ok(from_edn(text)?.deserialized()?)
+    }
+}
+
+impl<T> MimiContentEdn for T where T: MimiContentSerialize {}
+impl<T> MimiContentFromEdn for T where T: MimiContentDeserialize {}
+
+fn is_container(value: &Value) -> bool {
+    match value {
+        Value::Array(_) | Value::Map(_) => true,
+        Value::Tag(_, inner) => is_container(inner),
+        _ => false,
+    }
+}
+
+fn write_newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    out.extend(std::iter::repeat_n(' ', indent * 2));
+}
+
+fn write_items<'a, I>(out: &mut String, items: I, indent: Option<usize>, multiline: bool)
+where
+    I: ExactSizeIterator<Item = (&'a Value, Option<&'a Value>)>,
+{
+    let inner_indent = indent.map(|indent| indent + 1);
+    let count = items.len();
+    for (i, (first, second)) in items.enumerate() {
+        if let (true, Some(inner_indent)) = (multiline, inner_indent) {
+            write_newline(out, inner_indent);
+        }
+        write_value(out, first, inner_indent);
+        if let Some(second) = second {
+            out.push_str(": ");
+            write_value(out, second, inner_indent);
+        }
+        if i + 1 < count {
+            out.push(',');
+            if !multiline || indent.is_none() {
+                out.push(' ');
+            }
+        }
+    }
+    if let (true, Some(indent)) = (multiline, indent) {
+        write_newline(out, indent);
+    }
+}
+
+fn write_value(out: &mut String, value: &Value, indent: Option<usize>) {
+    use std::fmt::Write as _;
+
+    match value {
+        Value::Integer(int) => {
+            let _ = write!(out, "{}", i128::from(*int));
+        }
+        Value::Bytes(bytes) => {
+            out.push_str("h'");
+            for byte in bytes {
+                let _ = write!(out, "{byte:02x}");
+            }
+            out.push('\'');
+        }
+        Value::Float(float) => write_float(out, *float),
+        Value::Text(text) => write_text(out, text),
+        Value::Bool(boolean) => out.push_str(if *boolean { "true" } else { "false" }),
+        Value::Null => out.push_str("null"),
+        Value::Tag(tag, inner) => {
+            let _ = write!(out, "{tag}(");
+            write_value(out, inner, indent);
+            out.push(')');
+        }
+        Value::Array(items) => {
+            let multiline = indent.is_some() && items.iter().any(is_container);
+            out.push('[');
+            write_items(
+                out,
+                items.iter().map(|item| (item, None)),
+                indent,
+                multiline,
+            );
+            out.push(']');
+        }
+        Value::Map(entries) => {
+            let multiline = indent.is_some() && !entries.is_empty();
+            out.push('{');
+            write_items(
+                out,
+                entries.iter().map(|(k, v)| (k, Some(v))),
+                indent,
+                multiline,
+            );
+            out.push('}');
+        }
+        // `ciborium::Value` is non-exhaustive
+        _ => out.push_str("undefined"),
+    }
+}
+
+fn write_float(out: &mut String, float: f64) {
+    if float.is_nan() {
+        out.push_str("NaN");
+    } else if float.is_infinite() {
+        out.push_str(if float > 0.0 { "Infinity" } else { "-Infinity" });
+    } else {
+        // `Debug` always outputs a decimal point or an exponent, which keeps floats distinct from integers
+        out.push_str(&format!("{float:?}"));
+    }
+}
+
+pub(crate) fn write_text(out: &mut String, text: &str) {
+    use std::fmt::Write as _;
+
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let mut buf = [0u16; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    let _ = write!(out, "\\u{unit:04x}");
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// How deeply arrays, maps and tags may nest, well below what the stack can hold
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &'static str) -> MimiContentError {
+        MimiContentError::EdnError {
+            offset: self.pos,
+            reason,
+        }
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.pos += prefix.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char, reason: &'static str) -> Result<(), MimiContentError> {
+        self.skip_whitespace()?;
+        if self.bump() == Some(c) {
+            Ok(())
+        } else {
+            Err(self.error(reason))
+        }
+    }
+
+    /// Skips whitespace and `/ ... /` comments
+    fn skip_whitespace(&mut self) -> Result<(), MimiContentError> {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    self.bump();
+                    let Some(len) = self.rest().find('/') else {
+                        return Err(self.error("unterminated comment"));
+                    };
+                    self.pos += len + 1;
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, MimiContentError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        self.depth += 1;
+        let value = self.unnested_value();
+        self.depth -= 1;
+        value
+    }
+
+    fn unnested_value(&mut self) -> Result<Value, MimiContentError> {
+        self.skip_whitespace()?;
+        match self.peek() {
+            Some('[') => {
+                self.bump();
+                let (mut items, mut first) = (vec![], true);
+                while !self.end_of_container(']', &mut first)? {
+                    items.push(self.value()?);
+                }
+                Ok(Value::Array(items))
+            }
+            Some('{') => {
+                self.bump();
+                let (mut entries, mut first) = (vec![], true);
+                while !self.end_of_container('}', &mut first)? {
+                    let key = self.value()?;
+                    self.expect(':', "expected `:` after map key")?;
+                    entries.push((key, self.value()?));
+                }
+                Ok(Value::Map(entries))
+            }
+            Some('"') => Ok(Value::Text(self.text_string()?)),
+            Some('h') if self.eat("h'") => Ok(Value::Bytes(self.hex_bytes()?)),
+            Some('b') if self.eat("b64'") => Ok(Value::Bytes(self.base64_bytes()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(_) if self.eat("true") => Ok(Value::Bool(true)),
+            Some(_) if self.eat("false") => Ok(Value::Bool(false)),
+            Some(_) if self.eat("null") => Ok(Value::Null),
+            Some(_) if self.eat("NaN") => Ok(Value::Float(f64::NAN)),
+            Some(_) if self.eat("Infinity") => Ok(Value::Float(f64::INFINITY)),
+            _ => Err(self.error("expected a value")),
+        }
+    }
+
+    /// Consumes the separator between two container items, returning `true` once `close` is reached
+    fn end_of_container(
+        &mut self,
+        close: char,
+        first: &mut bool,
+    ) -> Result<bool, MimiContentError> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(close) {
+            self.bump();
+            return Ok(true);
+        }
+
+        if !std::mem::take(first) {
+            self.expect(',', "expected `,` between items")?;
+        }
+        Ok(false)
+    }
+
+    fn number(&mut self) -> Result<Value, MimiContentError> {
+        let start = self.pos;
+        if self.eat("-Infinity") {
+            return Ok(Value::Float(f64::NEG_INFINITY));
+        }
+        self.eat("-");
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '+' | '-') {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        let literal = &self.text[start..self.pos];
+
+        if self.peek() == Some('(') {
+            let tag = literal
+                .parse::<u64>()
+                .map_err(|_| self.error("invalid tag number"))?;
+            self.bump();
+            let inner = self.value()?;
+            self.expect(')', "expected `)` after tagged value")?;
+            return Ok(Value::Tag(tag, Box::new(inner)));
+        }
+
+        let is_hex = literal.trim_start_matches('-').starts_with("0x");
+        if !is_hex && literal.contains(['.', 'e', 'E']) {
+            return literal
+                .parse::<f64>()
+                .map(Value::Float)
+                .map_err(|_| self.error("invalid float"));
+        }
+
+        let int = match literal.strip_prefix('-') {
+            Some(digits) => parse_unsigned(digits).map(|int| -int),
+            None => parse_unsigned(literal),
+        }
+        .ok_or_else(|| self.error("invalid integer"))?;
+
+        ciborium::value::Integer::try_from(int)
+            .map(Value::Integer)
+            .map_err(|_| self.error("integer out of CBOR range"))
+    }
+
+    fn text_string(&mut self) -> Result<String, MimiContentError> {
+        self.bump(); // opening quote
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated text string")),
+                Some('"') => return Ok(text),
+                Some('\\') => match self.bump() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('/') => text.push('/'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('u') => text.push(self.unicode_escape()?),
+                    _ => return Err(self.error("invalid escape sequence")),
+                },
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    fn hex_unit(&mut self) -> Result<u16, MimiContentError> {
+        let digits = self
+            .rest()
+            .get(..4)
+            .ok_or_else(|| self.error("truncated unicode escape"))?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        let unit =
+            u16::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(unit)
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, MimiContentError> {
+        let high = self.hex_unit()?;
+        let units = if (0xD800..0xDC00).contains(&high) {
+            if !self.eat("\\u") {
+                return Err(self.error("unpaired surrogate"));
+            }
+            vec![high, self.hex_unit()?]
+        } else {
+            vec![high]
+        };
+
+        char::decode_utf16(units)
+            .next()
+            .and_then(Result::ok)
+            .ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn quoted_bytes_literal(&mut self) -> Result<String, MimiContentError> {
+        let Some(len) = self.rest().find('\'') else {
+            return Err(self.error("unterminated byte string"));
+        };
+        let literal = self.rest()[..len]
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        self.pos += len + 1;
+        Ok(literal)
+    }
+
+    fn hex_bytes(&mut self) -> Result<Vec<u8>, MimiContentError> {
+        let literal = self.quoted_bytes_literal()?;
+        if !literal.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid hex digit"));
+        }
+        if literal.len() % 2 != 0 {
+            return Err(self.error("odd number of hex digits"));
+        }
+        // ASCII only, so every pair of bytes is a pair of characters
+        (0..literal.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&literal[i..i + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|_| self.error("invalid hex digit"))
+    }
+
+    fn base64_bytes(&mut self) -> Result<Vec<u8>, MimiContentError> {
+        let literal = self.quoted_bytes_literal()?;
+        crate::json::base64url_decode(&literal).ok_or_else(|| self.error("invalid base64"))
+    }
+}
+
+fn parse_unsigned(digits: &str) -> Option<i128> {
+    match digits.strip_prefix("0x") {
+        Some(hex) if hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            i128::from_str_radix(hex, 16).ok()
+        }
+        Some(_) => None,
+        None if digits.bytes().all(|b| b.is_ascii_digit()) => digits.parse().ok(),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ciborium::Value;
+
+    use super::{from_edn, to_edn, to_edn_pretty};
+
+    #[test]
+    fn renders_and_parses_values() {
+        let value = Value::Array(vec![
+            Value::Bytes(vec![0x01, 0xab]),
+            Value::Integer((-17).into()),
+            Value::Float(1.5),
+            Value::Text("quote \" and \u{1}".into()),
+            Value::Null,
+            Value::Tag(
+                1001,
+                Box::new(Value::Map(vec![(
+                    Value::Integer(1.into()),
+                    Value::Bool(true),
+                )])),
+            ),
+        ]);
+
+        let edn = to_edn(&value);
+        assert_eq!(
+            edn,
+            r#"[h'01ab', -17, 1.5, "quote \" and \u0001", null, 1001({1: true})]"#
+        );
+        assert_eq!(from_edn(&edn).unwrap(), value);
+        assert_eq!(from_edn(&to_edn_pretty(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn parses_comments_and_alternate_literals() {
+        let value = from_edn("[ / salt / h'01 02', b64'AwQ', 0x10, \"\\ud83d\\ude00\" ]").unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::Bytes(vec![1, 2]),
+                Value::Bytes(vec![3, 4]),
+                Value::Integer(16.into()),
+                Value::Text("😀".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(from_edn("[1 2]").is_err());
+        assert!(from_edn("h'abc'").is_err());
+        assert!(from_edn("{1: 2").is_err());
+        assert!(from_edn("1 2").is_err());
+    }
+
+    #[test]
+    fn rejects_hostile_input_without_panicking() {
+        assert!(from_edn("h'aéb'").is_err());
+        assert!(from_edn("h'+1'").is_err());
+        assert!(from_edn("0x+1").is_err());
+        assert!(from_edn(r#""\u+041""#).is_err());
+        assert!(from_edn(r#""\u00é""#).is_err());
+        assert!(from_edn(&"[".repeat(100_000)).is_err());
+        let nested = format!("{}{}", "[".repeat(100), "]".repeat(100));
+        assert!(from_edn(&nested).is_ok());
+    }
+}
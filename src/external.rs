@@ -0,0 +1,759 @@
+//! Moving content out of messages into external storage, and back
+//!
+//! External content is encrypted with a [`ContentCipher`] (identified by its AEAD algorithm
+//! from the [IANA AEAD registry](https://www.iana.org/assignments/aead-parameters/)), and the
+//! `size` and `content_hash` of an [`ExternalPart`] describe the stored (encrypted) bytes.
+//! Parts are stored with an [`Uploader`] and resolved with a [`Fetcher`], which
+//! [`MemoryStore`] and [`DirectoryStore`] both implement.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use digest::Digest as _;
+
+use crate::{Bstr, ExternalPart, MimiContent, MimiContentError, NestedPartContent, SinglePart};
+
+/// SHA-256 in the [Named Information Hash Algorithm registry](https://www.iana.org/assignments/named-information/)
+pub const HASH_ALG_SHA256: u8 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalPartError {
+    #[error("The upload failed: {0}")]
+    Upload(String),
+    #[error("The content could not be encrypted")]
+    Encrypt,
+    #[error("The content expired at {expires}, it is now {now_secs}")]
+    Expired { expires: u32, now_secs: u64 },
+    #[error("The download failed: {0}")]
+    Fetch(String),
+    #[error("Expected {expected} bytes, downloaded {actual}")]
+    SizeMismatch { expected: u64, actual: u64 },
+    #[error("The content is larger than {limit} bytes")]
+    TooLarge { limit: u64 },
+    #[error("The size of the content is unknown")]
+    UnknownSize,
+    #[error("The content has no hash to verify it with")]
+    MissingHash,
+    #[error("The hash algorithm {0} is not supported")]
+    UnsupportedHashAlg(u8),
+    #[error("The downloaded content doesn't match the content hash")]
+    HashMismatch,
+    #[error("The AEAD algorithm {0} is not supported")]
+    UnsupportedEncAlg(u16),
+    #[error("The content could not be decrypted")]
+    Decrypt,
+}
+
+/// An AEAD used to encrypt external content
+pub trait ContentCipher {
+    /// The AEAD algorithm identifier, `0` when the content isn't encrypted
+    fn enc_alg(&self) -> u16;
+    fn key_len(&self) -> usize;
+    fn nonce_len(&self) -> usize;
+    fn seal(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, ExternalPartError>;
+    fn open(
+        &self,
+        key: &[u8],
+        nonce: &[u8],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, ExternalPartError>;
+}
+
+/// Stores external content unencrypted, for content that is public anyway
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unencrypted;
+
+impl ContentCipher for Unencrypted {
+    fn enc_alg(&self) -> u16 {
+        0
+    }
+
+    fn key_len(&self) -> usize {
+        0
+    }
+
+    fn nonce_len(&self) -> usize {
+        0
+    }
+
+    fn seal(
+        &self,
+        _key: &[u8],
+        _nonce: &[u8],
+        _aad: &[u8],
+        plaintext: &[u8],
+    ) -> Result<Vec<u8>, ExternalPartError> {
+        Ok(plaintext.to_vec())
+    }
+
+    fn open(
+        &self,
+        _key: &[u8],
+        _nonce: &[u8],
+        _aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Vec<u8>, ExternalPartError> {
+        Ok(ciphertext.to_vec())
+    }
+}
+
+/// Where the uploaded content can be downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadedContent {
+    pub url: String,
+    /// Seconds since the UNIX epoch after which the content is deleted, `0` if never
+    pub expires: u32,
+}
+
+pub trait Uploader {
+    /// Stores the (already encrypted) `content`
+    fn upload(&mut self, content: &[u8]) -> Result<UploadedContent, ExternalPartError>;
+}
+
+pub trait Fetcher {
+    /// Downloads the (still encrypted) content at `url`, failing with
+    /// [`ExternalPartError::TooLarge`] instead of reading more than `limit` bytes
+    fn fetch(&self, url: &str, limit: u64) -> Result<Vec<u8>, ExternalPartError>;
+}
+
+/// 100 MiB
+pub const DEFAULT_MAX_SIZE: u64 = 100 << 20;
+
+/// What [`ExternalPart::resolve_with`] accepts, besides parts with a known size and hash
+#[derive(Debug, Clone, PartialEq, Eq, bon::Builder)]
+pub struct ResolvePolicy {
+    /// Accept parts whose `hash_alg` is `0`, which then can't be verified
+    #[builder(default)]
+    pub allow_missing_hash: bool,
+    /// Accept parts whose `size` is `0`, downloading at most `max_size` bytes
+    #[builder(default)]
+    pub allow_unknown_size: bool,
+    #[builder(default = DEFAULT_MAX_SIZE)]
+    pub max_size: u64,
+}
+
+impl Default for ResolvePolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Keeps uploaded content in memory, under `memory://` URLs
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    pub contents: HashMap<String, Vec<u8>>,
+    /// The `expires` of every upload
+    pub expires: u32,
+}
+
+impl Uploader for MemoryStore {
+    fn upload(&mut self, content: &[u8]) -> Result<UploadedContent, ExternalPartError> {
+        let url = format!("memory://{}", self.contents.len());
+        self.contents.insert(url.clone(), content.to_vec());
+        Ok(UploadedContent {
+            url,
+            expires: self.expires,
+        })
+    }
+}
+
+impl Fetcher for MemoryStore {
+    fn fetch(&self, url: &str, limit: u64) -> Result<Vec<u8>, ExternalPartError> {
+        let content = self
+            .contents
+            .get(url)
+            .ok_or_else(|| ExternalPartError::Fetch(format!("{url} not found")))?;
+        if content.len() as u64 > limit {
+            return Err(ExternalPartError::TooLarge { limit });
+        }
+        Ok(content.clone())
+    }
+}
+
+/// Writes uploaded content to files named by their hash in a local directory, under
+/// `file://` URLs
+#[derive(Debug, Clone)]
+pub struct DirectoryStore {
+    pub directory: PathBuf,
+}
+
+impl DirectoryStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+}
+
+impl Uploader for DirectoryStore {
+    fn upload(&mut self, content: &[u8]) -> Result<UploadedContent, ExternalPartError> {
+        let name: String = sha2::Sha256::digest(content)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let path = self.directory.join(name);
+        std::fs::write(&path, content).map_err(|e| ExternalPartError::Upload(e.to_string()))?;
+        let path = path
+            .canonicalize()
+            .map_err(|e| ExternalPartError::Upload(e.to_string()))?;
+        Ok(UploadedContent {
+            url: format!("file://{}", path.display()),
+            expires: 0,
+        })
+    }
+}
+
+impl Fetcher for DirectoryStore {
+    /// Only reads `file://` URLs of files inside the directory
+    fn fetch(&self, url: &str, limit: u64) -> Result<Vec<u8>, ExternalPartError> {
+        use std::io::Read as _;
+
+        let fetch_error = |reason: &dyn std::fmt::Display| {
+            ExternalPartError::Fetch(format!("cannot read {url}: {reason}"))
+        };
+        let path = url
+            .strip_prefix("file://")
+            .ok_or_else(|| fetch_error(&"not a file URL"))?;
+        let path = std::path::Path::new(path)
+            .canonicalize()
+            .map_err(|e| fetch_error(&e))?;
+        let directory = self.directory.canonicalize().map_err(|e| fetch_error(&e))?;
+        if !path.starts_with(directory) {
+            return Err(fetch_error(&"outside of the store directory"));
+        }
+        let mut content = vec![];
+        std::fs::File::open(path)
+            .and_then(|file| file.take(limit.saturating_add(1)).read_to_end(&mut content))
+            .map_err(|e| fetch_error(&e))?;
+        if content.len() as u64 > limit {
+            return Err(ExternalPartError::TooLarge { limit });
+        }
+        Ok(content)
+    }
+}
+
+impl ExternalPart {
+    /// Downloads, verifies and decrypts the content, with one of `ciphers`, under the default
+    /// [`ResolvePolicy`]: the `size` and `content_hash` must be present and match.
+    pub fn resolve(
+        &self,
+        fetcher: &impl Fetcher,
+        ciphers: &[&dyn ContentCipher],
+        now_secs: u64,
+    ) -> Result<Vec<u8>, MimiContentError> {
+        self.resolve_with(fetcher, ciphers, now_secs, &ResolvePolicy::default())
+    }
+
+    /// Like [`Self::resolve`], under `policy`
+    pub fn resolve_with(
+        &self,
+        fetcher: &impl Fetcher,
+        ciphers: &[&dyn ContentCipher],
+        now_secs: u64,
+        policy: &ResolvePolicy,
+    ) -> Result<Vec<u8>, MimiContentError> {
+        if self.expires != 0 && now_secs > u64::from(self.expires) {
+            return Err(ExternalPartError::Expired {
+                expires: self.expires,
+                now_secs,
+            }
+            .into());
+        }
+
+        let cipher = ciphers
+            .iter()
+            .find(|cipher| cipher.enc_alg() == self.enc_alg)
+            .ok_or(ExternalPartError::UnsupportedEncAlg(self.enc_alg))?;
+
+        match self.hash_alg {
+            0 if !policy.allow_missing_hash => return Err(ExternalPartError::MissingHash.into()),
+            0 | HASH_ALG_SHA256 => {}
+            hash_alg => return Err(ExternalPartError::UnsupportedHashAlg(hash_alg).into()),
+        }
+        let limit = match self.size {
+            0 if !policy.allow_unknown_size => return Err(ExternalPartError::UnknownSize.into()),
+            0 => policy.max_size,
+            size if size > policy.max_size => {
+                return Err(ExternalPartError::TooLarge {
+                    limit: policy.max_size,
+                }
+                .into())
+            }
+            size => size,
+        };
+
+        let stored = fetcher.fetch(&self.url, limit)?;
+
+        if self.size != 0 && self.size != stored.len() as u64 {
+            return Err(ExternalPartError::SizeMismatch {
+                expected: self.size,
+                actual: stored.len() as u64,
+            }
+            .into());
+        }
+        if self.hash_alg == HASH_ALG_SHA256
+            && sha2::Sha256::digest(&stored).as_slice() != &*self.content_hash
+        {
+            return Err(ExternalPartError::HashMismatch.into());
+        }
+
+        Ok(cipher.open(&self.key, &self.nonce, &self.aad, &stored)?)
+    }
+
+    /// Like [`Self::resolve`], returning a single part whose content type carries the
+    /// filename as its `name` parameter
+    pub fn resolve_to_single_part(
+        &self,
+        fetcher: &impl Fetcher,
+        ciphers: &[&dyn ContentCipher],
+        now_secs: u64,
+    ) -> Result<SinglePart, MimiContentError> {
+        let content = self.resolve(fetcher, ciphers, now_secs)?;
+        let content_type = match self.media_type() {
+            Ok(media_type)
+                if !self.filename.is_empty() && media_type.parameter("name").is_none() =>
+            {
+                media_type.with_filename(&*self.filename).into()
+            }
+            _ => self.content_type.clone(),
+        };
+        Ok(SinglePart {
+            content_type,
+            content: content.into(),
+        })
+    }
+
+    /// Encrypts `single` with a fresh key and nonce and uploads it.
+    ///
+    /// The filename is taken from the `name` parameter of the content type, if any.
+    pub fn upload(
+        single: &SinglePart,
+        uploader: &mut impl Uploader,
+        cipher: &impl ContentCipher,
+        csprng: &mut dyn rand_core::CryptoRngCore,
+    ) -> Result<Self, MimiContentError> {
+        let mut key = vec![0; cipher.key_len()];
+        let mut nonce = vec![0; cipher.nonce_len()];
+        csprng.fill_bytes(&mut key);
+        csprng.fill_bytes(&mut nonce);
+
+        let stored = cipher.seal(&key, &nonce, &[], &single.content)?;
+        let uploaded = uploader.upload(&stored)?;
+        let filename = single
+            .media_type()
+            .ok()
+            .and_then(|media_type| media_type.parameter("name").map(str::to_string))
+            .unwrap_or_default();
+
+        Ok(Self {
+            content_type: single.content_type.clone(),
+            url: uploaded.url.into(),
+            expires: uploaded.expires,
+            size: stored.len() as u64,
+            enc_alg: cipher.enc_alg(),
+            key: key.into(),
+            nonce: nonce.into(),
+            aad: Bstr::default(),
+            hash_alg: HASH_ALG_SHA256,
+            content_hash: sha2::Sha256::digest(&stored).to_vec().into(),
+            description: Default::default(),
+            filename: filename.into(),
+        })
+    }
+}
+
+impl MimiContent {
+    /// Replaces every single part larger than `threshold` bytes by an external part (see
+    /// [`ExternalPart::upload`]), keeping the disposition and language of the nested part.
+    ///
+    /// Returns the paths of the externalized parts. The message is only modified once every
+    /// upload succeeded, and is left unchanged otherwise (content that was already uploaded
+    /// isn't deleted).
+    pub fn externalize_large_parts(
+        &mut self,
+        threshold: usize,
+        uploader: &mut impl Uploader,
+        cipher: &impl ContentCipher,
+        csprng: &mut dyn rand_core::CryptoRngCore,
+    ) -> Result<Vec<Vec<usize>>, MimiContentError> {
+        let paths: Vec<_> = self
+            .nested_part
+            .iter_parts()
+            .filter(|part| match &part.part.part_content {
+                NestedPartContent::SinglePart(single) => single.content.len() > threshold,
+                _ => false,
+            })
+            .map(|part| part.path)
+            .collect();
+
+        let mut externals = Vec::with_capacity(paths.len());
+        for path in &paths {
+            let part = self.get(path).expect("the path was just collected");
+            let NestedPartContent::SinglePart(single) = &part.part_content else {
+                unreachable!("only single parts are collected");
+            };
+            externals.push(ExternalPart::upload(single, uploader, cipher, csprng)?);
+        }
+        for (path, external) in paths.iter().zip(externals) {
+            let part = self.get_mut(path).expect("the path was just collected");
+            part.part_content = NestedPartContent::ExternalPart(external);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest as _;
+    use rand::SeedableRng as _;
+
+    use super::*;
+    use crate::{BaseDispos, Disposition, MediaType, MultiPart, NestedPart, PartSemantics};
+
+    /// Not an AEAD, only used to check that the stored bytes are the encrypted ones
+    struct XorCipher;
+
+    impl ContentCipher for XorCipher {
+        fn enc_alg(&self) -> u16 {
+            0xFFFF
+        }
+
+        fn key_len(&self) -> usize {
+            16
+        }
+
+        fn nonce_len(&self) -> usize {
+            12
+        }
+
+        fn seal(
+            &self,
+            key: &[u8],
+            _nonce: &[u8],
+            _aad: &[u8],
+            plaintext: &[u8],
+        ) -> Result<Vec<u8>, ExternalPartError> {
+            Ok(plaintext
+                .iter()
+                .zip(key.iter().cycle())
+                .map(|(byte, key)| byte ^ key)
+                .collect())
+        }
+
+        fn open(
+            &self,
+            key: &[u8],
+            nonce: &[u8],
+            aad: &[u8],
+            ciphertext: &[u8],
+        ) -> Result<Vec<u8>, ExternalPartError> {
+            if key.len() != self.key_len() {
+                return Err(ExternalPartError::Decrypt);
+            }
+            self.seal(key, nonce, aad, ciphertext)
+        }
+    }
+
+    fn message_with_attachment() -> MimiContent {
+        let part = |content_type: MediaType, content: &[u8]| NestedPart {
+            disposition: Disposition::Base(BaseDispos::Attachment),
+            language: "en".into(),
+            part_content: NestedPartContent::SinglePart(SinglePart {
+                content_type: content_type.into(),
+                content: content.to_vec().into(),
+            }),
+            ..Default::default()
+        };
+        MimiContent::builder()
+            .salt_from_outside_entropy(Default::default())
+            .topic_id(Default::default())
+            .nested_part(NestedPart {
+                part_content: NestedPartContent::MultiPart(MultiPart {
+                    part_semantics: PartSemantics::ProcessAll,
+                    parts: vec![
+                        part(MediaType::TEXT_PLAIN, b"see attached"),
+                        part(
+                            MediaType::new("application", "pdf").with_filename("report.pdf"),
+                            &[0x25; 4096],
+                        ),
+                    ],
+                }),
+                ..Default::default()
+            })
+            .build()
+    }
+
+    #[test]
+    fn externalizes_large_parts() {
+        let mut mimi_content = message_with_attachment();
+        let mut store = MemoryStore::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+
+        let paths = mimi_content
+            .externalize_large_parts(1024, &mut store, &XorCipher, &mut rng)
+            .unwrap();
+        assert_eq!(paths, vec![vec![1]]);
+
+        let part = mimi_content.get(&[1]).unwrap();
+        assert_eq!(part.disposition, Disposition::Base(BaseDispos::Attachment));
+        assert_eq!(&*part.language, "en");
+        let NestedPartContent::ExternalPart(external) = &part.part_content else {
+            panic!("expected an external part, got {:?}", part.part_content);
+        };
+        assert_eq!(&*external.filename, "report.pdf");
+        assert_eq!(external.key.len(), 16);
+
+        let stored = &store.contents[&*external.url];
+        assert_ne!(stored, &vec![0x25; 4096]);
+        assert_eq!(external.size, stored.len() as u64);
+        assert_eq!(
+            &*external.content_hash,
+            sha2::Sha256::digest(stored).as_slice()
+        );
+
+        assert!(matches!(
+            mimi_content.get(&[0]).unwrap().part_content,
+            NestedPartContent::SinglePart(_)
+        ));
+    }
+
+    #[test]
+    fn keeps_the_message_when_an_upload_fails() {
+        /// Fails once `successes` contents were stored
+        struct FailingUploader {
+            store: MemoryStore,
+            successes: usize,
+        }
+
+        impl Uploader for FailingUploader {
+            fn upload(&mut self, content: &[u8]) -> Result<UploadedContent, ExternalPartError> {
+                if self.store.contents.len() == self.successes {
+                    return Err(ExternalPartError::Upload("quota exceeded".into()));
+                }
+                self.store.upload(content)
+            }
+        }
+
+        let mut mimi_content = message_with_attachment();
+        let NestedPartContent::MultiPart(multi) = &mut mimi_content.nested_part.part_content else {
+            panic!("expected a multipart");
+        };
+        multi.parts.push(multi.parts[1].clone());
+        let original = mimi_content.clone();
+
+        let mut uploader = FailingUploader {
+            store: MemoryStore::default(),
+            successes: 1,
+        };
+        let result = mimi_content.externalize_large_parts(
+            1024,
+            &mut uploader,
+            &XorCipher,
+            &mut rand::rngs::StdRng::seed_from_u64(0),
+        );
+        assert!(matches!(
+            result,
+            Err(MimiContentError::ExternalPart(ExternalPartError::Upload(_)))
+        ));
+        assert_eq!(uploader.store.contents.len(), 1);
+        assert_eq!(mimi_content, original);
+    }
+
+    #[test]
+    fn uploads_to_a_directory() {
+        let directory = std::env::temp_dir().join(format!("mimi-content-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut store = DirectoryStore::new(&directory);
+
+        let mut mimi_content = message_with_attachment();
+        mimi_content
+            .externalize_large_parts(
+                1024,
+                &mut store,
+                &Unencrypted,
+                &mut rand::rngs::StdRng::seed_from_u64(0),
+            )
+            .unwrap();
+        let NestedPartContent::ExternalPart(external) =
+            &mimi_content.get(&[1]).unwrap().part_content
+        else {
+            panic!("expected an external part");
+        };
+        assert_eq!(external.enc_alg, 0);
+        let path = external.url.strip_prefix("file://").unwrap();
+        assert_eq!(std::fs::read(path).unwrap(), vec![0x25; 4096]);
+        assert_eq!(
+            external.resolve(&store, &[&Unencrypted], 0).unwrap(),
+            vec![0x25; 4096]
+        );
+        assert!(matches!(
+            store.fetch("file:///etc/hostname", DEFAULT_MAX_SIZE),
+            Err(ExternalPartError::Fetch(_))
+        ));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    fn externalized() -> (ExternalPart, MemoryStore) {
+        let mut mimi_content = message_with_attachment();
+        let mut store = MemoryStore {
+            expires: 1_700_000_000,
+            ..Default::default()
+        };
+        mimi_content
+            .externalize_large_parts(
+                1024,
+                &mut store,
+                &XorCipher,
+                &mut rand::rngs::StdRng::seed_from_u64(0),
+            )
+            .unwrap();
+        let NestedPartContent::ExternalPart(external) = mimi_content
+            .replace(&[1], NestedPart::default())
+            .unwrap()
+            .part_content
+        else {
+            panic!("expected an external part");
+        };
+        (external, store)
+    }
+
+    #[test]
+    fn resolves_external_parts() {
+        let (external, store) = externalized();
+        let ciphers: [&dyn ContentCipher; 2] = [&Unencrypted, &XorCipher];
+
+        let single = external
+            .resolve_to_single_part(&store, &ciphers, 1_600_000_000)
+            .unwrap();
+        assert_eq!(&*single.content, &[0x25; 4096]);
+        let media_type = single.media_type().unwrap();
+        assert_eq!(media_type.essence(), "application/pdf");
+        assert_eq!(media_type.parameter("name"), Some("report.pdf"));
+    }
+
+    #[test]
+    fn reports_each_failed_resolution_step() {
+        let (external, mut store) = externalized();
+        let ciphers: [&dyn ContentCipher; 1] = [&XorCipher];
+        let error = |external: &ExternalPart, store: &MemoryStore| match external.resolve(
+            store,
+            &ciphers,
+            1_600_000_000,
+        ) {
+            Err(MimiContentError::ExternalPart(error)) => error,
+            other => panic!("expected an external part error, got {other:?}"),
+        };
+
+        assert!(matches!(
+            external.resolve(&store, &ciphers, 1_800_000_000),
+            Err(MimiContentError::ExternalPart(
+                ExternalPartError::Expired { .. }
+            ))
+        ));
+        assert!(matches!(
+            error(&external, &MemoryStore::default()),
+            ExternalPartError::Fetch(_)
+        ));
+        assert!(matches!(
+            external.resolve(&store, &[&Unencrypted], 0),
+            Err(MimiContentError::ExternalPart(
+                ExternalPartError::UnsupportedEncAlg(0xFFFF)
+            ))
+        ));
+
+        let unsupported_hash = ExternalPart {
+            hash_alg: 42,
+            ..external.clone()
+        };
+        assert!(matches!(
+            error(&unsupported_hash, &store),
+            ExternalPartError::UnsupportedHashAlg(42)
+        ));
+        let wrong_key = ExternalPart {
+            key: vec![0; 8].into(),
+            ..external.clone()
+        };
+        assert!(matches!(
+            error(&wrong_key, &store),
+            ExternalPartError::Decrypt
+        ));
+
+        let stored = store.contents.get_mut(&*external.url).unwrap();
+        stored[0] ^= 1;
+        assert!(matches!(
+            error(&external, &store),
+            ExternalPartError::HashMismatch
+        ));
+        store.contents.get_mut(&*external.url).unwrap().pop();
+        assert!(matches!(
+            error(&external, &store),
+            ExternalPartError::SizeMismatch {
+                expected: 4096,
+                actual: 4095
+            }
+        ));
+        store
+            .contents
+            .get_mut(&*external.url)
+            .unwrap()
+            .extend([0; 2]);
+        assert!(matches!(
+            error(&external, &store),
+            ExternalPartError::TooLarge { limit: 4096 }
+        ));
+    }
+
+    #[test]
+    fn requires_a_size_and_hash_unless_allowed() {
+        let (external, store) = externalized();
+        let ciphers: [&dyn ContentCipher; 1] = [&XorCipher];
+        let unverified = ExternalPart {
+            hash_alg: 0,
+            size: 0,
+            ..external.clone()
+        };
+        assert!(matches!(
+            unverified.resolve(&store, &ciphers, 0),
+            Err(MimiContentError::ExternalPart(
+                ExternalPartError::MissingHash
+            ))
+        ));
+        let unhashed = ResolvePolicy::builder().allow_missing_hash(true).build();
+        assert!(matches!(
+            unverified.resolve_with(&store, &ciphers, 0, &unhashed),
+            Err(MimiContentError::ExternalPart(
+                ExternalPartError::UnknownSize
+            ))
+        ));
+
+        let lenient = ResolvePolicy::builder()
+            .allow_missing_hash(true)
+            .allow_unknown_size(true)
+            .build();
+        assert_eq!(
+            unverified
+                .resolve_with(&store, &ciphers, 0, &lenient)
+                .unwrap(),
+            vec![0x25; 4096]
+        );
+        let small = ResolvePolicy {
+            max_size: 1024,
+            ..lenient
+        };
+        for external in [&unverified, &external] {
+            assert!(matches!(
+                external.resolve_with(&store, &ciphers, 0, &small),
+                Err(MimiContentError::ExternalPart(
+                    ExternalPartError::TooLarge { limit: 1024 }
+                ))
+            ));
+        }
+    }
+}
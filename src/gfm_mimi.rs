@@ -1,4 +1,7 @@
-use comrak::RenderPlugins;
+use comrak::{
+    nodes::{AstNode, ListType, NodeValue},
+    RenderPlugins,
+};
 
 pub struct GfmMimiRenderer<'a> {
     options: comrak::Options<'a>,
@@ -34,6 +37,12 @@ impl GfmMimiRenderer<'_> {
         }
     }
 
+    /// Whether `:shortcode:`s are rendered as emoji, which is off by default
+    pub fn with_shortcodes(mut self, shortcodes: bool) -> Self {
+        self.options.extension.shortcodes = shortcodes;
+        self
+    }
+
     pub fn gfm_mimi_to_html(&self, markdown: &str) -> String {
         comrak::markdown_to_html_with_plugins(
             markdown,
@@ -51,6 +60,97 @@ impl GfmMimiRenderer<'_> {
     pub fn gfm_mimi_to_commonmark(&self, markdown: &str) -> String {
         comrak::markdown_to_commonmark(markdown, &self.options)
     }
+
+    /// Renders the markdown as unformatted text, for recipients that don't support GFM-MIMI.
+    ///
+    /// Blocks are separated by blank lines, list items keep a `-` or `1.` marker,
+    /// links keep their URL in parentheses and images are replaced by their description.
+    pub fn gfm_mimi_to_plain_text(&self, markdown: &str) -> String {
+        let arena = comrak::Arena::new();
+        let root = comrak::parse_document(&arena, markdown, &self.options);
+        plain_text_blocks(root, "\n\n")
+    }
+}
+
+fn plain_text_blocks<'a>(node: &'a AstNode<'a>, separator: &str) -> String {
+    node.children()
+        .map(plain_text_block)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn plain_text_block<'a>(node: &'a AstNode<'a>) -> String {
+    match &node.data.borrow().value {
+        NodeValue::List(list) => {
+            let separator = if list.tight { "\n" } else { "\n\n" };
+            node.children()
+                .enumerate()
+                .map(|(i, item)| {
+                    let marker = match list.list_type {
+                        ListType::Bullet => "- ".to_string(),
+                        ListType::Ordered => format!("{}. ", list.start + i),
+                    };
+                    let check = match item.data.borrow().value {
+                        NodeValue::TaskItem(Some(_)) => "[x] ",
+                        NodeValue::TaskItem(None) => "[ ] ",
+                        _ => "",
+                    };
+                    let text = check.to_string() + &plain_text_blocks(item, separator);
+                    let indented = text.replace('\n', &format!("\n{}", " ".repeat(marker.len())));
+                    marker + &indented
+                })
+                .collect::<Vec<_>>()
+                .join(separator)
+        }
+        NodeValue::BlockQuote | NodeValue::MultilineBlockQuote(_) | NodeValue::Alert(_) => {
+            plain_text_blocks(node, "\n\n")
+                .lines()
+                .map(|line| format!("> {line}").trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        NodeValue::CodeBlock(code_block) => code_block.literal.trim_end_matches('\n').to_string(),
+        NodeValue::Table(_) => node
+            .children()
+            .map(|row| {
+                row.children()
+                    .map(plain_text_inlines)
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        NodeValue::ThematicBreak => "---".to_string(),
+        NodeValue::HtmlBlock(_) | NodeValue::FrontMatter(_) => String::new(),
+        NodeValue::Paragraph | NodeValue::Heading(_) => plain_text_inlines(node),
+        _ => plain_text_blocks(node, "\n\n"),
+    }
+}
+
+fn plain_text_inlines<'a>(node: &'a AstNode<'a>) -> String {
+    let mut text = String::new();
+    for child in node.children() {
+        match &child.data.borrow().value {
+            NodeValue::Text(literal) => text.push_str(literal),
+            NodeValue::Code(code) => text.push_str(&code.literal),
+            NodeValue::Math(math) => text.push_str(&math.literal),
+            NodeValue::ShortCode(short_code) => text.push_str(&short_code.emoji),
+            NodeValue::SoftBreak | NodeValue::LineBreak => text.push('\n'),
+            NodeValue::HtmlInline(_) | NodeValue::Raw(_) => {}
+            NodeValue::Link(link) => {
+                let label = plain_text_inlines(child);
+                let url = link.url.strip_prefix("mailto:").unwrap_or(&link.url);
+                if label == url || url.is_empty() {
+                    text.push_str(&label);
+                } else {
+                    text.push_str(&format!("{label} ({url})"));
+                }
+            }
+            _ => text.push_str(&plain_text_inlines(child)),
+        }
+    }
+    text
 }
 
 impl Default for GfmMimiRenderer<'_> {
@@ -58,3 +158,65 @@ impl Default for GfmMimiRenderer<'_> {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::GfmMimiRenderer;
+
+    #[test]
+    fn renders_plain_text() {
+        let markdown = "\
+# Release *2.0*
+
+Hi **everyone**, see [the notes](https://example.com/notes) or <https://example.com>.
+
+- [x] ship `mimi`
+- [ ] celebrate
+
+1. first
+2. second
+
+> quoted
+> text
+
+| a | b |
+|---|---|
+| 1 | 2 |
+";
+        let expected = "\
+Release 2.0
+
+Hi everyone, see the notes (https://example.com/notes) or https://example.com.
+
+- [x] ship mimi
+- [ ] celebrate
+
+1. first
+2. second
+
+> quoted
+> text
+
+a | b
+1 | 2";
+        pretty_assertions::assert_eq!(
+            GfmMimiRenderer::new().gfm_mimi_to_plain_text(markdown),
+            expected
+        );
+    }
+
+    #[test]
+    fn renders_shortcodes_when_enabled() {
+        let markdown = "ship it :rocket:";
+        assert_eq!(
+            GfmMimiRenderer::new().gfm_mimi_to_html(markdown),
+            "<p>ship it :rocket:</p>\n"
+        );
+        assert_eq!(
+            GfmMimiRenderer::new()
+                .with_shortcodes(true)
+                .gfm_mimi_to_html(markdown),
+            "<p>ship it 🚀</p>\n"
+        );
+    }
+}
@@ -0,0 +1,411 @@
+//! JSON view of the wire types, meant for logs and debugging.
+//!
+//! The mapping is one-way, to a [`serde_json::Value`]:
+//!
+//! | Wire type                  | JSON                                                         |
+//! |----------------------------|--------------------------------------------------------------|
+//! | `bstr` (ids, salt, content) | base64url string without padding                            |
+//! | `tstr`, `uint`, `bool`     | string, number, boolean                                      |
+//! | absent optional field      | `null`                                                       |
+//! | [`Disposition`]            | `"render"`, `"reaction"`... or a number for extensions       |
+//! | cardinality                | `"nullPart"`, `"singlePart"`, `"externalPart"`, `"multiPart"` |
+//! | [`PartSemantics`]          | `"chooseOne"`, `"singleUnit"`, `"processAll"`                |
+//! | [`MessageStatus`]          | `"unread"`, `"delivered"`... or a number for extensions      |
+//! | extensions                 | array of `{"name": ..., "value": ...}` objects               |
+//! | unknown trailing fields    | `unknownFields` array, omitted when empty                    |
+//! | generic CBOR values        | [RFC8949 Section 6.1](https://www.rfc-editor.org/rfc/rfc8949.html#name-converting-from-cbor-to-js) conversion |
+//!
+//! Struct fields are mapped to objects whose keys are the camelCase names of the fields.
+
+use serde_json::Value as JsonValue;
+
+use crate::{
+    delivery_report::{MessageBaseStatus, MessageStatus, MessageStatusReport, PerMessageStatus},
+    derived::MessageDerivedValues,
+    BaseDispos, Disposition, Expiration, MessageId, MimiContent, NestedPart, NestedPartContent,
+    PartSemantics, Timestamp, Value,
+};
+
+const BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes `bytes` as unpadded base64url
+pub fn base64url_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buf = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]);
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL_ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+/// Decodes base64url or base64, with or without padding
+pub fn base64url_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let sextet = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'-' | b'+' => 62,
+            b'_' | b'/' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | u32::from(sextet);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    // A single dangling sextet can't encode a full byte
+    (bits < 6).then_some(out)
+}
+
+fn bytes(bytes: &[u8]) -> JsonValue {
+    JsonValue::String(base64url_encode(bytes))
+}
+
+fn string(text: &str) -> JsonValue {
+    JsonValue::String(text.to_string())
+}
+
+fn object<const N: usize>(fields: [(&str, JsonValue); N]) -> JsonValue {
+    JsonValue::Object(
+        fields
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+    )
+}
+
+fn integer(int: i128) -> JsonValue {
+    i64::try_from(int)
+        .map(JsonValue::from)
+        .or_else(|_| u64::try_from(int).map(JsonValue::from))
+        // beyond 64 bits, JSON numbers are only approximate
+        .unwrap_or_else(|_| JsonValue::from(int as f64))
+}
+
+/// Converts a generic CBOR value, non-finite floats becoming `null`
+fn from_cbor(value: &ciborium::Value) -> JsonValue {
+    use ciborium::Value as Cbor;
+
+    match value {
+        Cbor::Integer(int) => integer(i128::from(*int)),
+        Cbor::Bytes(content) => bytes(content),
+        Cbor::Float(float) => JsonValue::from(*float),
+        Cbor::Text(text) => string(text),
+        Cbor::Bool(boolean) => JsonValue::Bool(*boolean),
+        Cbor::Tag(_, inner) => from_cbor(inner),
+        Cbor::Array(items) => JsonValue::Array(items.iter().map(from_cbor).collect()),
+        Cbor::Map(entries) => JsonValue::Object(
+            entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        Cbor::Text(text) => text.clone(),
+                        key => from_cbor(key).to_string(),
+                    };
+                    (key, from_cbor(value))
+                })
+                .collect(),
+        ),
+        _ => JsonValue::Null,
+    }
+}
+
+/// Types that have a JSON view (see the [module documentation](self) for the mapping)
+pub trait MimiContentJson {
+    fn to_json_value(&self) -> JsonValue;
+
+    fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    /// Indented with two spaces
+    fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json_value()).expect("JSON values always serialize")
+    }
+}
+
+impl<T: MimiContentJson> MimiContentJson for Option<T> {
+    fn to_json_value(&self) -> JsonValue {
+        self.as_ref()
+            .map_or(JsonValue::Null, MimiContentJson::to_json_value)
+    }
+}
+
+impl MimiContentJson for Value {
+    fn to_json_value(&self) -> JsonValue {
+        from_cbor(&ciborium::Value::from(self.0.clone()))
+    }
+}
+
+impl MimiContentJson for MessageId {
+    fn to_json_value(&self) -> JsonValue {
+        bytes(&**self)
+    }
+}
+
+impl MimiContentJson for Timestamp {
+    fn to_json_value(&self) -> JsonValue {
+        match self {
+            Self::MsecsSinceEpoch(msecs) => integer((*msecs).into()),
+            Self::ExtendedTime(extended) => JsonValue::Object(
+                extended
+                    .0
+                    .iter()
+                    .map(|(name, value)| {
+                        let key = match name {
+                            crate::Name::Int(int) => int.to_string(),
+                            crate::Name::Str(tstr) => tstr.to_string(),
+                        };
+                        (key, value.to_json_value())
+                    })
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl MimiContentJson for Expiration {
+    fn to_json_value(&self) -> JsonValue {
+        object([
+            ("relative", JsonValue::Bool(self.relative)),
+            ("time", integer(self.time.into())),
+        ])
+    }
+}
+
+impl MimiContentJson for Disposition {
+    fn to_json_value(&self) -> JsonValue {
+        let name = match self {
+            Self::Base(BaseDispos::Unspecified) => "unspecified",
+            Self::Base(BaseDispos::Render) => "render",
+            Self::Base(BaseDispos::Reaction) => "reaction",
+            Self::Base(BaseDispos::Profile) => "profile",
+            Self::Base(BaseDispos::Inline) => "inline",
+            Self::Base(BaseDispos::Icon) => "icon",
+            Self::Base(BaseDispos::Attachment) => "attachment",
+            Self::Base(BaseDispos::Session) => "session",
+            Self::Base(BaseDispos::Preview) => "preview",
+            Self::Ext(ext) => return integer((*ext).into()),
+        };
+        string(name)
+    }
+}
+
+impl MimiContentJson for PartSemantics {
+    fn to_json_value(&self) -> JsonValue {
+        string(match self {
+            Self::ChooseOne => "chooseOne",
+            Self::SingleUnit => "singleUnit",
+            Self::ProcessAll => "processAll",
+        })
+    }
+}
+
+impl MimiContentJson for NestedPart {
+    fn to_json_value(&self) -> JsonValue {
+        let mut fields = serde_json::Map::new();
+        fields.insert("disposition".to_string(), self.disposition.to_json_value());
+        fields.insert("language".to_string(), string(&self.language));
+        let mut push = |key: &str, value: JsonValue| fields.insert(key.to_string(), value);
+
+        match &self.part_content {
+            NestedPartContent::NullPart => {
+                push("cardinality", string("nullPart"));
+            }
+            NestedPartContent::SinglePart(single) => {
+                push("cardinality", string("singlePart"));
+                push("contentType", string(&single.content_type));
+                push("content", bytes(&single.content));
+            }
+            NestedPartContent::ExternalPart(external) => {
+                push("cardinality", string("externalPart"));
+                push("contentType", string(&external.content_type));
+                push("url", string(&external.url));
+                push("expires", integer(external.expires.into()));
+                push("size", integer(external.size.into()));
+                push("encAlg", integer(external.enc_alg.into()));
+                push("key", bytes(&external.key));
+                push("nonce", bytes(&external.nonce));
+                push("aad", bytes(&external.aad));
+                push("hashAlg", integer(external.hash_alg.into()));
+                push("contentHash", bytes(&external.content_hash));
+                push("description", string(&external.description));
+                push("filename", string(&external.filename));
+            }
+            NestedPartContent::MultiPart(multi) => {
+                push("cardinality", string("multiPart"));
+                push("partSemantics", multi.part_semantics.to_json_value());
+                push(
+                    "parts",
+                    JsonValue::Array(multi.parts.iter().map(Self::to_json_value).collect()),
+                );
+            }
+            NestedPartContent::Unknown {
+                cardinality,
+                raw_fields,
+            } => {
+                push("cardinality", integer((*cardinality).into()));
+                push(
+                    "rawFields",
+                    JsonValue::Array(raw_fields.iter().map(Value::to_json_value).collect()),
+                );
+            }
+        }
+        if !self.unknown_fields.is_empty() {
+            push(
+                "unknownFields",
+                JsonValue::Array(
+                    self.unknown_fields
+                        .iter()
+                        .map(Value::to_json_value)
+                        .collect(),
+                ),
+            );
+        }
+
+        JsonValue::Object(fields)
+    }
+}
+
+impl MimiContentJson for MimiContent {
+    fn to_json_value(&self) -> JsonValue {
+        let extensions = self
+            .extensions
+            .iter()
+            .map(|(name, value)| {
+                let name = match name {
+                    crate::Name::Int(int) => integer((*int).into()),
+                    crate::Name::Str(tstr) => string(tstr),
+                };
+                object([("name", name), ("value", value.to_json_value())])
+            })
+            .collect();
+
+        let mut json = object([
+            ("salt", bytes(&self.salt)),
+            ("replaces", self.replaces.to_json_value()),
+            ("topicId", bytes(&self.topic_id)),
+            ("expires", self.expires.to_json_value()),
+            ("inReplyTo", self.in_reply_to.to_json_value()),
+            ("extensions", JsonValue::Array(extensions)),
+            ("nestedPart", self.nested_part.to_json_value()),
+        ]);
+        if let (JsonValue::Object(fields), false) = (&mut json, self.unknown_fields.is_empty()) {
+            fields.insert(
+                "unknownFields".to_string(),
+                JsonValue::Array(
+                    self.unknown_fields
+                        .iter()
+                        .map(Value::to_json_value)
+                        .collect(),
+                ),
+            );
+        }
+        json
+    }
+}
+
+impl MimiContentJson for MessageStatus {
+    fn to_json_value(&self) -> JsonValue {
+        let name = match self {
+            Self::Base(MessageBaseStatus::Unread) => "unread",
+            Self::Base(MessageBaseStatus::Delivered) => "delivered",
+            Self::Base(MessageBaseStatus::Read) => "read",
+            Self::Base(MessageBaseStatus::Expired) => "expired",
+            Self::Base(MessageBaseStatus::Deleted) => "deleted",
+            Self::Base(MessageBaseStatus::Hidden) => "hidden",
+            Self::Base(MessageBaseStatus::Error) => "error",
+            Self::Ext(ext) => return integer((*ext).into()),
+        };
+        string(name)
+    }
+}
+
+impl MimiContentJson for PerMessageStatus {
+    fn to_json_value(&self) -> JsonValue {
+        object([
+            ("messageId", self.message_id.to_json_value()),
+            ("status", self.status.to_json_value()),
+            ("timestamp", self.timestamp.to_json_value()),
+            ("extensionData", self.extension_data.to_json_value()),
+        ])
+    }
+}
+
+impl MimiContentJson for MessageStatusReport {
+    fn to_json_value(&self) -> JsonValue {
+        JsonValue::Array(self.0.iter().map(PerMessageStatus::to_json_value).collect())
+    }
+}
+
+impl MimiContentJson for MessageDerivedValues {
+    fn to_json_value(&self) -> JsonValue {
+        object([
+            ("messageId", self.message_id.to_json_value()),
+            (
+                "hubAcceptedTimestamp",
+                self.hub_accepted_timestamp.to_json_value(),
+            ),
+            ("mlsGroupId", bytes(&self.mls_group_id)),
+            ("senderLeafIndex", integer(self.sender_leaf_index.into())),
+            ("senderClientUrl", string(&self.sender_client_url)),
+            ("senderUserUrl", string(&self.sender_user_url)),
+            ("roomUrl", string(&self.room_url)),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{delivery_report::MessageStatusReport, MimiContent, MimiContentDeserialize as _};
+
+    use super::{base64url_decode, base64url_encode, MimiContentJson as _};
+
+    #[test]
+    fn base64url_roundtrips() {
+        for len in 0..8 {
+            let bytes: Vec<u8> = (0..len).map(|i| 0xf0 + i).collect();
+            assert_eq!(base64url_decode(&base64url_encode(&bytes)).unwrap(), bytes);
+        }
+        assert_eq!(base64url_encode(b"\xfb\xff"), "-_8");
+        assert_eq!(base64url_decode("+/8=").unwrap(), b"\xfb\xff");
+        assert!(base64url_decode("A").is_none());
+    }
+
+    #[test]
+    fn reaction_uses_names() {
+        let mimi_content =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/reaction.cbor"))
+                .unwrap();
+        let json = mimi_content.nested_part.to_json();
+        assert_eq!(
+            json,
+            r#"{"disposition":"reaction","language":"","cardinality":"singlePart","contentType":"text/plain;charset=utf-8","content":"4p2k"}"#
+        );
+    }
+
+    #[test]
+    fn report_uses_names() {
+        let report =
+            MessageStatusReport::from_cbor_bytes(include_bytes!("../tests/examples/report.cbor"))
+                .unwrap();
+        let json = report.to_json_pretty();
+        assert!(json.contains(r#""status": "read""#), "{json}");
+        assert!(json.contains(r#""timestamp": null"#), "{json}");
+    }
+}
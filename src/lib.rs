@@ -1,24 +1,53 @@
 #![warn(clippy::all)]
 
+pub mod attachment;
+mod charset;
+mod codec;
 mod common;
 pub mod delivery_report;
 pub mod derived;
+pub mod diff;
 mod dispositions;
+pub mod edn;
+pub mod external;
 #[cfg(feature = "gfm-mimi")]
 pub mod gfm_mimi;
+pub mod json;
+mod media_type;
 mod message_id;
+#[cfg(feature = "mls")]
+pub mod mls;
+mod negotiation;
 mod nested_part;
-// mod rfc9581; // WIP: this is complex and should probably live in another crate altogether
+mod payload;
+pub mod preview;
+pub mod profile;
+pub mod reaction;
+mod rfc9581;
+pub mod safety;
+pub mod session;
+pub mod sniff;
+mod visit;
+mod wire_version;
+mod writer;
 
 pub mod reexports {
     pub use ciborium;
     #[cfg(feature = "gfm-mimi")]
     pub use comrak;
+    pub use emojis;
 }
+pub use charset::*;
 pub use common::*;
 pub use dispositions::*;
+pub use media_type::*;
 pub use message_id::*;
+pub use negotiation::*;
 pub use nested_part::*;
+pub use payload::*;
+pub use visit::*;
+pub use wire_version::*;
+pub use writer::*;
 
 use indexmap::IndexMap;
 
@@ -135,17 +164,8 @@ impl std::ops::Deref for RoomUriExtension {
     }
 }
 
-#[derive(
-    Debug,
-    Default,
-    Clone,
-    PartialEq,
-    Eq,
-    serde_tuple::Serialize_tuple,
-    serde_tuple::Deserialize_tuple,
-)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct MimiContent {
-    #[serde(with = "serde_bytes")]
     salt: MimiContentSalt,
     pub replaces: Option<MessageId>,
     pub topic_id: Bstr,
@@ -153,6 +173,10 @@ pub struct MimiContent {
     pub in_reply_to: Option<MessageId>,
     pub extensions: IndexMap<Name, Value>,
     pub nested_part: NestedPart,
+    /// Trailing fields defined by a newer version of the format, re-encoded so that message
+    /// ids and franking tags stay stable. This holds for received bytes in preferred
+    /// (shortest, definite-length) CBOR encodings, which ciborium's values normalize to.
+    pub unknown_fields: Vec<Value>,
 }
 
 #[bon::bon]
@@ -175,6 +199,7 @@ impl MimiContent {
             in_reply_to,
             extensions,
             nested_part,
+            unknown_fields: vec![],
         }
     }
 }
@@ -214,9 +239,8 @@ impl<S: mimi_content_builder::State> MimiContentBuilder<S> {
 
 impl MimiContent {
     /// Hashes the CBOR bytes of `self`
-    pub fn hash<H: digest::Digest>(&self) -> Result<Bstr, MimiContentError> {
-        let bytes = self.to_cbor_bytes()?;
-        let hash = H::digest(&bytes);
+    pub fn hash<H: digest::Digest + digest::Update>(&self) -> Result<Bstr, MimiContentError> {
+        let hash = self.cbor_digest_update(H::new())?.finalize();
         Ok(hash.to_vec().into())
     }
 
@@ -243,9 +267,9 @@ impl MimiContent {
     /// This should belong in mimi-content as noted in this issue <https://github.com/ietf-wg-mimi/mimi-protocol/issues/91> so it is implemented here under a feature flag
     pub fn calculate_franking_tag(&self) -> Result<FrankingTag, MimiContentError> {
         use hmac::Mac as _;
-        let mut hmac = hmac::SimpleHmac::<sha2::Sha256>::new_from_slice(&self.salt).unwrap(); // SAFETY: HMAC can take keys of any size because of OPAD
+        let hmac = hmac::SimpleHmac::<sha2::Sha256>::new_from_slice(&self.salt).unwrap(); // SAFETY: HMAC can take keys of any size because of OPAD
 
-        hmac.update(&self.to_cbor_bytes()?);
+        let hmac = self.cbor_digest_update(hmac)?;
 
         let franking_tag = hmac.finalize().into_bytes();
         Ok(FrankingTag(franking_tag.into()))
@@ -279,16 +303,16 @@ impl FrankingTag {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde_tuple::Serialize_tuple)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MimiContentRef<'a> {
-    #[serde(with = "serde_bytes")]
     pub salt: &'a MimiContentSalt,
     pub replaces: Option<MessageIdRef<'a>>,
     pub topic_id: BstrRef<'a>,
     pub expires: Option<&'a Expiration>,
     pub in_reply_to: Option<MessageIdRef<'a>>,
-    pub extensions: IndexMap<NameRef<'a>, ValueRef<'a>>,
+    pub extensions: &'a IndexMap<Name, Value>,
     pub nested_part: NestedPartRef<'a>,
+    pub unknown_fields: &'a [Value],
 }
 
 impl MimiContentAsRef for MimiContent {
@@ -300,12 +324,9 @@ impl MimiContentAsRef for MimiContent {
             topic_id: self.topic_id.as_ref(),
             expires: self.expires.as_ref(),
             in_reply_to: self.in_reply_to.as_ref().map(MessageId::as_ref),
-            extensions: self
-                .extensions
-                .iter()
-                .map(|(k, v)| (k.as_ref(), v.as_ref()))
-                .collect(),
+            extensions: &self.extensions,
             nested_part: self.nested_part.as_ref(),
+            unknown_fields: &self.unknown_fields,
         }
     }
 }
@@ -323,6 +344,41 @@ pub enum MimiContentError {
     #[error("The custom Hash Algorithm is out of the custom range (64..u8::MAX)")]
     CustomMessageIdHashAlgOutOfRange(u8),
     #[error(transparent)]
+    ValueError(#[from] ciborium::value::Error),
+    #[error("Invalid diagnostic notation at offset {offset}: {reason}")]
+    EdnError { offset: usize, reason: &'static str },
+    #[error("The message cannot be encoded for {version}: {reason}")]
+    NotRepresentable {
+        version: WireVersion,
+        reason: &'static str,
+    },
+    #[error("Invalid media type {0:?}")]
+    InvalidMediaType(String),
+    #[error("Unsupported charset {0:?}")]
+    UnsupportedCharset(String),
+    #[error("Invalid {charset} sequence at byte offset {offset}")]
+    InvalidText { charset: Charset, offset: usize },
+    #[error("The content cannot be rendered by the recipient: {0}")]
+    NotRenderable(String),
+    #[error("Invalid MLS application data framing: {0}")]
+    InvalidMlsFraming(&'static str),
+    #[error(transparent)]
+    ExternalPart(#[from] external::ExternalPartError),
+    #[error("Invalid link preview: {0}")]
+    InvalidLinkPreview(&'static str),
+    #[error("Invalid contact card: {0}")]
+    InvalidContactCard(&'static str),
+    #[error("Invalid session invite: {0}")]
+    InvalidSessionInvite(&'static str),
+    #[error("Invalid reaction: {0}")]
+    InvalidReaction(&'static str),
+    #[error("Invalid attachment: {0}")]
+    InvalidAttachment(&'static str),
+    #[error(transparent)]
+    UnsafeUrl(#[from] safety::UrlViolation),
+    #[error("The content type {0:?} is not a MIMI payload")]
+    UnexpectedContentType(String),
+    #[error(transparent)]
     IoError(#[from] std::io::Error),
 }
 
@@ -342,9 +398,27 @@ pub trait MimiContentSerialize: serde::Serialize {
     }
 
     fn to_cbor_bytes_into(&self, buf: &mut Vec<u8>) -> Result<(), MimiContentError> {
-        ciborium::into_writer(self, buf)?;
+        self.to_cbor_writer(buf)
+    }
+
+    fn to_cbor_writer<W: std::io::Write>(&self, writer: W) -> Result<(), MimiContentError> {
+        ciborium::into_writer(self, writer)?;
         Ok(())
     }
+
+    /// The length of the CBOR encoding, computed without allocating it
+    fn encoded_len(&self) -> Result<usize, MimiContentError> {
+        let mut writer = CountingWriter::new(std::io::sink());
+        self.to_cbor_writer(&mut writer)?;
+        Ok(writer.count())
+    }
+
+    /// Feeds the CBOR encoding into `digest` (a [`digest::Digest`] or a MAC)
+    fn cbor_digest_update<D: digest::Update>(&self, digest: D) -> Result<D, MimiContentError> {
+        let mut writer = DigestWriter::new(digest);
+        self.to_cbor_writer(&mut writer)?;
+        Ok(writer.into_inner())
+    }
 }
 
 pub trait MimiContentDeserialize: serde::de::DeserializeOwned {
@@ -0,0 +1,336 @@
+use std::borrow::Cow;
+
+use crate::{ExternalPart, MimiContentError, SinglePart};
+
+type Parameter = (Cow<'static, str>, Cow<'static, str>);
+
+/// A parsed media type, as found in `content_type` fields (see [RFC9110 Section 8.3.1](https://www.rfc-editor.org/rfc/rfc9110.html#name-media-type))
+///
+/// Type, subtype and parameter names are compared case-insensitively, as are the values
+/// of the `charset` parameter. Parameter order doesn't matter.
+#[derive(Debug, Clone)]
+pub struct MediaType {
+    type_: Cow<'static, str>,
+    subtype: Cow<'static, str>,
+    parameters: Cow<'static, [Parameter]>,
+}
+
+pub(crate) const fn media_type(
+    type_: &'static str,
+    subtype: &'static str,
+    parameters: &'static [Parameter],
+) -> MediaType {
+    MediaType {
+        type_: Cow::Borrowed(type_),
+        subtype: Cow::Borrowed(subtype),
+        parameters: Cow::Borrowed(parameters),
+    }
+}
+
+impl MediaType {
+    pub const TEXT_PLAIN: Self = media_type("text", "plain", &[]);
+    pub const TEXT_PLAIN_UTF8: Self = media_type(
+        "text",
+        "plain",
+        &[(Cow::Borrowed("charset"), Cow::Borrowed("utf-8"))],
+    );
+    pub const TEXT_HTML: Self = media_type("text", "html", &[]);
+    pub const TEXT_MARKDOWN_GFM_MIMI: Self = media_type(
+        "text",
+        "markdown",
+        &[(Cow::Borrowed("variant"), Cow::Borrowed("GFM-MIMI"))],
+    );
+    pub const TEXT_URI_LIST: Self = media_type("text", "uri-list", &[]);
+    pub const APPLICATION_MIMI_CONTENT: Self = media_type("application", "mimi-content", &[]);
+    pub const APPLICATION_MIMI_MESSAGE_STATUS: Self =
+        media_type("application", "mimi-message-status", &[]);
+    pub const APPLICATION_CBOR: Self = media_type("application", "cbor", &[]);
+
+    pub fn new(type_: impl Into<String>, subtype: impl Into<String>) -> Self {
+        Self {
+            type_: Cow::Owned(type_.into()),
+            subtype: Cow::Owned(subtype.into()),
+            parameters: Cow::Borrowed(&[]),
+        }
+    }
+
+    /// Adds a parameter, replacing any parameter with the same name
+    pub fn with_parameter(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        let parameters = self.parameters.to_mut();
+        parameters.retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+        parameters.push((Cow::Owned(name), Cow::Owned(value.into())));
+        self
+    }
+
+    /// Sets the `name` parameter, which becomes the filename of an externalized part
+    pub fn with_filename(self, filename: impl Into<String>) -> Self {
+        self.with_parameter("name", filename)
+    }
+
+    /// Parses a `Content-Type` value such as `text/plain;charset=utf-8`
+    pub fn parse(text: &str) -> Result<Self, MimiContentError> {
+        let invalid = || MimiContentError::InvalidMediaType(text.to_string());
+
+        let (essence, mut rest) = text.split_once(';').unwrap_or((text, ""));
+        let (type_, subtype) = essence.trim().split_once('/').ok_or_else(invalid)?;
+        if !is_token(type_) || !is_token(subtype) {
+            return Err(invalid());
+        }
+
+        let mut media_type = Self::new(type_, subtype);
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ';']);
+            if rest.is_empty() {
+                break;
+            }
+
+            let (name, after_name) = rest.split_once('=').ok_or_else(invalid)?;
+            let name = name.trim();
+            if !is_token(name) {
+                return Err(invalid());
+            }
+
+            let (value, after_value) = match after_name.trim_start().strip_prefix('"') {
+                Some(quoted) => {
+                    let (value, after_value) = parse_quoted_string(quoted).ok_or_else(invalid)?;
+                    let after_value = after_value.trim_start();
+                    if !after_value.is_empty() && !after_value.starts_with(';') {
+                        return Err(invalid());
+                    }
+                    (value, after_value)
+                }
+                None => {
+                    let (value, after_value) =
+                        after_name.split_once(';').unwrap_or((after_name, ""));
+                    let value = value.trim();
+                    if !is_token(value) {
+                        return Err(invalid());
+                    }
+                    (value.to_string(), after_value)
+                }
+            };
+
+            media_type = media_type.with_parameter(name, value);
+            rest = after_value;
+        }
+
+        Ok(media_type)
+    }
+
+    #[inline]
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    #[inline]
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    /// The media type without its parameters, e.g. `text/plain`
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype).to_ascii_lowercase()
+    }
+
+    pub fn parameters(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.parameters
+            .iter()
+            .map(|(name, value)| (name.as_ref(), value.as_ref()))
+    }
+
+    /// Looks up a parameter by its case-insensitive name
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters()
+            .find(|(existing, _)| existing.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value)
+    }
+
+    #[inline]
+    pub fn charset(&self) -> Option<&str> {
+        self.parameter("charset")
+    }
+
+    /// The markdown variant, e.g. `GFM-MIMI`
+    #[inline]
+    pub fn variant(&self) -> Option<&str> {
+        self.parameter("variant")
+    }
+
+    /// The codecs of an audio or video type, split on commas
+    pub fn codecs(&self) -> Vec<&str> {
+        self.parameter("codecs")
+            .map(|codecs| codecs.split(',').map(str::trim).collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether both media types have the same type and subtype, ignoring parameters
+    pub fn same_essence(&self, other: &Self) -> bool {
+        self.type_.eq_ignore_ascii_case(&other.type_)
+            && self.subtype.eq_ignore_ascii_case(&other.subtype)
+    }
+
+    /// Whether `self` matches `pattern`, where `pattern` may use `*` as a type or subtype
+    /// wildcard, and only the parameters of `pattern` must be present in `self`
+    pub fn matches(&self, pattern: &Self) -> bool {
+        let matches_part =
+            |part: &str, pattern: &str| pattern == "*" || part.eq_ignore_ascii_case(pattern);
+        matches_part(&self.type_, &pattern.type_)
+            && matches_part(&self.subtype, &pattern.subtype)
+            && pattern
+                .parameters()
+                .all(|(name, value)| self.parameter_eq(name, value))
+    }
+
+    #[inline]
+    pub fn is_text(&self) -> bool {
+        self.type_.eq_ignore_ascii_case("text")
+    }
+
+    fn parameter_eq(&self, name: &str, value: &str) -> bool {
+        self.parameter(name).is_some_and(|existing| {
+            if name.eq_ignore_ascii_case("charset") {
+                existing.eq_ignore_ascii_case(value)
+            } else {
+                existing == value
+            }
+        })
+    }
+}
+
+impl PartialEq for MediaType {
+    fn eq(&self, other: &Self) -> bool {
+        self.same_essence(other)
+            && self.parameters.len() == other.parameters.len()
+            && other
+                .parameters()
+                .all(|(name, value)| self.parameter_eq(name, value))
+    }
+}
+
+impl Eq for MediaType {}
+
+impl std::fmt::Display for MediaType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in self.parameters() {
+            if is_token(value) {
+                write!(f, ";{name}={value}")?;
+            } else {
+                write!(f, ";{name}=\"")?;
+                for c in value.chars() {
+                    if matches!(c, '"' | '\\') {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{c}")?;
+                }
+                write!(f, "\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for MediaType {
+    type Err = MimiContentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<&MediaType> for crate::Tstr {
+    fn from(value: &MediaType) -> Self {
+        value.to_string().into()
+    }
+}
+
+impl From<MediaType> for crate::Tstr {
+    fn from(value: MediaType) -> Self {
+        Self::from(&value)
+    }
+}
+
+fn is_token(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Parses the remainder of a quoted string (after the opening quote), returning the
+/// unescaped value and what follows the closing quote
+fn parse_quoted_string(text: &str) -> Option<(String, &str)> {
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 1..])),
+            '\\' => value.push(chars.next()?.1),
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+impl SinglePart {
+    pub fn media_type(&self) -> Result<MediaType, MimiContentError> {
+        MediaType::parse(&self.content_type)
+    }
+}
+
+impl ExternalPart {
+    pub fn media_type(&self) -> Result<MediaType, MimiContentError> {
+        MediaType::parse(&self.content_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MediaType;
+
+    #[test]
+    fn parses_spec_content_types() {
+        let markdown = MediaType::parse("text/markdown;variant=GFM-MIMI").unwrap();
+        assert_eq!(markdown, MediaType::TEXT_MARKDOWN_GFM_MIMI);
+        assert_eq!(markdown.variant(), Some("GFM-MIMI"));
+        assert_eq!(markdown.to_string(), "text/markdown;variant=GFM-MIMI");
+
+        let plain = MediaType::parse("Text/Plain; Charset=\"UTF-8\"").unwrap();
+        assert_eq!(plain, MediaType::TEXT_PLAIN_UTF8);
+        assert_ne!(plain, MediaType::TEXT_PLAIN);
+        assert!(plain.matches(&MediaType::TEXT_PLAIN));
+        assert!(plain.matches(&MediaType::new("text", "*")));
+        assert_eq!(plain.charset(), Some("UTF-8"));
+    }
+
+    #[test]
+    fn handles_parameters() {
+        let video = MediaType::parse(r#"video/mp4; codecs="avc1.42E01E, mp4a.40.2"; x=1"#).unwrap();
+        assert_eq!(video.codecs(), vec!["avc1.42E01E", "mp4a.40.2"]);
+        assert_eq!(video.parameter("X"), Some("1"));
+        assert_eq!(
+            video.to_string(),
+            r#"video/mp4;codecs="avc1.42E01E, mp4a.40.2";x=1"#
+        );
+        assert_eq!(MediaType::parse(&video.to_string()).unwrap(), video);
+
+        let reordered =
+            MediaType::parse(r#"video/mp4;x=1;codecs="avc1.42E01E, mp4a.40.2""#).unwrap();
+        assert_eq!(reordered, video);
+    }
+
+    #[test]
+    fn rejects_invalid_media_types() {
+        for invalid in [
+            "",
+            "text",
+            "text/",
+            "te xt/plain",
+            "text/plain;charset",
+            "text/plain;a=\"b",
+        ] {
+            assert!(MediaType::parse(invalid).is_err(), "{invalid:?}");
+        }
+    }
+}
@@ -3,12 +3,12 @@ use crate::{MimiContent, MimiContentAsRef, MimiContentError, MimiContentSerializ
 const MESSAGE_ID_SIZE: usize = 32;
 
 /// See https://www.ietf.org/archive/id/draft-ietf-mimi-content-04.html#name-message-id-and-accepted-tim
-#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 #[repr(transparent)]
 #[serde(transparent)]
 pub struct MessageId(serde_bytes::ByteArray<MESSAGE_ID_SIZE>);
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, serde::Serialize)]
 #[repr(transparent)]
 #[serde(transparent)]
 pub struct MessageIdRef<'a>(#[serde(with = "serde_bytes")] &'a [u8; MESSAGE_ID_SIZE]);
@@ -84,17 +84,18 @@ impl MessageId {
     /// * `hash_alg` - A MessageID hash algorithm identifier
     ///
     /// For the other arguments, see [`Self::construct`]
-    fn construct_with_custom_alg<H: digest::Digest>(
+    #[allow(clippy::int_plus_one)]
+    fn construct_with_custom_alg<H: digest::Digest + digest::Update>(
         hash_alg: u8,
         sender_uri: TstrRef,
         room_uri: TstrRef,
         mimi_content: &MimiContent,
     ) -> Result<Self, MimiContentError> {
-        let mimi_content_bytes = mimi_content.to_cbor_bytes()?;
         let digest = H::new()
             .chain_update(sender_uri.as_bytes())
-            .chain_update(room_uri.as_bytes())
-            .chain_update(mimi_content_bytes)
+            .chain_update(room_uri.as_bytes());
+        let digest = mimi_content
+            .cbor_digest_update(digest)?
             .chain_update(mimi_content.salt)
             .finalize();
 
@@ -130,7 +131,10 @@ mod tests {
         .unwrap();
     }
 
-    fn build_message_id_custom<H: digest::Digest>(mimi_content: &MimiContent, hash_alg: u8) {
+    fn build_message_id_custom<H: digest::Digest + digest::Update>(
+        mimi_content: &MimiContent,
+        hash_alg: u8,
+    ) {
         let _message_id = MessageId::construct_with_custom_alg::<H>(
             hash_alg,
             Tstr::from("mimi://u/alice.smith").as_ref(),
@@ -150,6 +154,7 @@ mod tests {
             in_reply_to: None,
             extensions: Default::default(),
             nested_part: Default::default(),
+            unknown_fields: vec![],
         };
 
         // Sha256
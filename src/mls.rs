@@ -0,0 +1,328 @@
+//! Framing of MIMI payloads inside MLS application messages
+//!
+//! Application data is framed with the content type it carries, as defined by the
+//! [MLS extensions draft](https://datatracker.ietf.org/doc/draft-ietf-mls-extensions/):
+//!
+//! ```text
+//! struct {
+//!     opaque media_type<V>;
+//!     opaque application_content<V>;
+//! } ApplicationFraming;
+//! ```
+//!
+//! This module doesn't depend on an MLS implementation: adapt the received messages of your
+//! MLS library with [`MlsApplicationMessage`], and pass [`MimiPayload::to_mls_application_data`]
+//! to its `create_message`.
+
+use crate::{
+    delivery_report::MessageStatusReport,
+    derived::{MessageDerivedValues, MsgUri},
+    MediaType, MessageId, MimiContent, MimiContentAsRef as _, MimiContentError, MimiPayload,
+    Timestamp,
+};
+
+/// Largest length that can be encoded as an MLS variable-length integer
+const MAX_VARINT: usize = (1 << 30) - 1;
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidMlsFraming(reason)
+}
+
+/// Writes `len` as an MLS variable-length integer ([RFC9420 Section 2.1.2](https://www.rfc-editor.org/rfc/rfc9420.html#section-2.1.2))
+fn write_varint(buf: &mut Vec<u8>, len: usize) -> Result<(), MimiContentError> {
+    match len {
+        0..=0x3F => buf.push(len as u8),
+        0x40..=0x3FFF => buf.extend_from_slice(&(0x4000 | len as u16).to_be_bytes()),
+        0x4000..=MAX_VARINT => buf.extend_from_slice(&(0x8000_0000 | len as u32).to_be_bytes()),
+        _ => return Err(invalid("the length doesn't fit a variable-length integer")),
+    }
+    Ok(())
+}
+
+fn read_varint(bytes: &[u8]) -> Result<(usize, &[u8]), MimiContentError> {
+    let first = *bytes.first().ok_or_else(|| invalid("truncated length"))?;
+    let size = 1 << (first >> 6);
+    if size > 4 {
+        return Err(invalid("invalid length prefix"));
+    }
+    let (encoded, rest) = bytes
+        .split_at_checked(size)
+        .ok_or_else(|| invalid("truncated length"))?;
+    let len = encoded[1..]
+        .iter()
+        .fold(usize::from(first & 0x3F), |len, &byte| {
+            (len << 8) | usize::from(byte)
+        });
+    // RFC9420 requires the minimum encoding
+    if size > 1 && len < 1 << (8 * size / 2 - 2) {
+        return Err(invalid("non-minimal length encoding"));
+    }
+    Ok((len, rest))
+}
+
+fn write_opaque(buf: &mut Vec<u8>, bytes: &[u8]) -> Result<(), MimiContentError> {
+    write_varint(buf, bytes.len())?;
+    buf.extend_from_slice(bytes);
+    Ok(())
+}
+
+fn read_opaque(bytes: &[u8]) -> Result<(&[u8], &[u8]), MimiContentError> {
+    let (len, rest) = read_varint(bytes)?;
+    rest.split_at_checked(len)
+        .ok_or_else(|| invalid("truncated opaque value"))
+}
+
+/// Frames `content` as MLS application data of type `media_type`
+pub fn frame_application_data(
+    media_type: &MediaType,
+    content: &[u8],
+) -> Result<Vec<u8>, MimiContentError> {
+    let media_type = media_type.to_string();
+    let mut buf = Vec::with_capacity(media_type.len() + content.len() + 8);
+    write_opaque(&mut buf, media_type.as_bytes())?;
+    write_opaque(&mut buf, content)?;
+    Ok(buf)
+}
+
+/// Splits MLS application data into its media type and content
+pub fn unframe_application_data(bytes: &[u8]) -> Result<(MediaType, &[u8]), MimiContentError> {
+    let (media_type, rest) = read_opaque(bytes)?;
+    let (content, rest) = read_opaque(rest)?;
+    if !rest.is_empty() {
+        return Err(invalid("trailing bytes after the application content"));
+    }
+    let media_type = std::str::from_utf8(media_type)
+        .map_err(|_| invalid("the media type is not UTF-8"))?
+        .parse()?;
+    Ok((media_type, content))
+}
+
+impl MimiPayload {
+    /// Frames the payload for [`MlsApplicationMessage::application_data`]
+    pub fn to_mls_application_data(&self) -> Result<Vec<u8>, MimiContentError> {
+        frame_application_data(
+            &MediaType::parse(self.content_type())?,
+            &self.to_cbor_bytes()?,
+        )
+    }
+
+    pub fn from_mls_application_data(bytes: &[u8]) -> Result<Self, MimiContentError> {
+        let (media_type, content) = unframe_application_data(bytes)?;
+        Self::decode(&media_type.to_string(), content)
+    }
+}
+
+impl MimiContent {
+    pub fn to_mls_application_data(&self) -> Result<Vec<u8>, MimiContentError> {
+        frame_application_data(
+            &MediaType::APPLICATION_MIMI_CONTENT,
+            &crate::MimiContentSerialize::to_cbor_bytes(self)?,
+        )
+    }
+}
+
+impl MessageStatusReport {
+    pub fn to_mls_application_data(&self) -> Result<Vec<u8>, MimiContentError> {
+        frame_application_data(
+            &MediaType::APPLICATION_MIMI_MESSAGE_STATUS,
+            &crate::MimiContentSerialize::to_cbor_bytes(self)?,
+        )
+    }
+}
+
+/// A received and decrypted MLS application message, as exposed by the MLS library in use
+pub trait MlsApplicationMessage {
+    fn group_id(&self) -> &[u8];
+    fn sender_leaf_index(&self) -> u32;
+    fn application_data(&self) -> &[u8];
+}
+
+/// What the hub and the credential of the sender tell about a received message
+#[derive(Debug, Clone, bon::Builder)]
+pub struct MlsSenderContext {
+    pub hub_accepted_timestamp: Timestamp,
+    pub sender_client_url: MsgUri,
+    pub sender_user_url: MsgUri,
+    pub room_url: MsgUri,
+}
+
+/// A MIMI payload received through MLS
+#[derive(Debug, Clone)]
+pub struct ReceivedMimiPayload {
+    pub payload: MimiPayload,
+    /// Absent for standalone status reports, which have no message id
+    pub derived_values: Option<MessageDerivedValues>,
+}
+
+impl MessageDerivedValues {
+    /// Derives the values of `mimi_content`, received in `message`
+    pub fn from_mls_message(
+        message: &impl MlsApplicationMessage,
+        mimi_content: &MimiContent,
+        context: MlsSenderContext,
+    ) -> Result<Self, MimiContentError> {
+        let message_id = MessageId::construct(
+            context.sender_user_url.as_ref(),
+            context.room_url.as_ref(),
+            mimi_content,
+        )?;
+        Ok(Self {
+            message_id,
+            hub_accepted_timestamp: context.hub_accepted_timestamp,
+            mls_group_id: message.group_id().to_vec().into(),
+            sender_leaf_index: message.sender_leaf_index(),
+            sender_client_url: context.sender_client_url,
+            sender_user_url: context.sender_user_url,
+            room_url: context.room_url,
+        })
+    }
+}
+
+impl ReceivedMimiPayload {
+    /// Decodes the application data of `message` and derives the values of received content
+    pub fn from_mls_message(
+        message: &impl MlsApplicationMessage,
+        context: MlsSenderContext,
+    ) -> Result<Self, MimiContentError> {
+        let payload = MimiPayload::from_mls_application_data(message.application_data())?;
+        let derived_values = payload
+            .mimi_content()
+            .map(|mimi_content| {
+                MessageDerivedValues::from_mls_message(message, mimi_content, context)
+            })
+            .transpose()?;
+        Ok(Self {
+            payload,
+            derived_values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MimiContentDeserialize as _, MimiContentSerialize as _};
+
+    struct ReceivedMessage {
+        group_id: Vec<u8>,
+        sender_leaf_index: u32,
+        application_data: Vec<u8>,
+    }
+
+    impl MlsApplicationMessage for ReceivedMessage {
+        fn group_id(&self) -> &[u8] {
+            &self.group_id
+        }
+
+        fn sender_leaf_index(&self) -> u32 {
+            self.sender_leaf_index
+        }
+
+        fn application_data(&self) -> &[u8] {
+            &self.application_data
+        }
+    }
+
+    #[test]
+    fn varints_use_the_minimal_encoding() {
+        for (len, encoded) in [
+            (0, &[0x00][..]),
+            (37, &[0x25]),
+            (15293, &[0x7B, 0xBD]),
+            (494878333, &[0x9D, 0x7F, 0x3E, 0x7D]),
+        ] {
+            let mut buf = vec![];
+            write_varint(&mut buf, len).unwrap();
+            assert_eq!(buf, encoded);
+            assert_eq!(read_varint(encoded).unwrap(), (len, &[][..]));
+        }
+
+        assert!(read_varint(&[0x40, 0x25]).is_err());
+        assert!(read_varint(&[0xC0, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(write_varint(&mut vec![], MAX_VARINT + 1).is_err());
+    }
+
+    #[test]
+    fn frames_application_data() {
+        let data = frame_application_data(&MediaType::APPLICATION_MIMI_CONTENT, b"\x80").unwrap();
+        assert_eq!(data, b"\x18application/mimi-content\x01\x80");
+        let (media_type, content) = unframe_application_data(&data).unwrap();
+        assert_eq!(media_type, MediaType::APPLICATION_MIMI_CONTENT);
+        assert_eq!(content, b"\x80");
+
+        assert!(unframe_application_data(&data[..data.len() - 1]).is_err());
+        assert!(unframe_application_data(&[data.as_slice(), b"\0"].concat()).is_err());
+    }
+
+    #[test]
+    fn derives_values_from_received_messages() {
+        let bytes = include_bytes!("../tests/examples/original.cbor");
+        let mimi_content = MimiContent::from_cbor_bytes(bytes).unwrap();
+        let message = ReceivedMessage {
+            group_id: b"group".to_vec(),
+            sender_leaf_index: 3,
+            application_data: mimi_content.to_mls_application_data().unwrap(),
+        };
+        let context = MlsSenderContext::builder()
+            .hub_accepted_timestamp(Timestamp::MsecsSinceEpoch(1644387225000))
+            .sender_client_url("mimi://example.com/d/alice-smith/phone".into())
+            .sender_user_url("mimi://example.com/u/alice-smith".into())
+            .room_url("mimi://example.com/r/engineering_team".into())
+            .build();
+
+        let received = ReceivedMimiPayload::from_mls_message(&message, context).unwrap();
+        let MimiPayload::Content(received_content) = &received.payload else {
+            panic!("expected content, got {:?}", received.payload);
+        };
+        assert_eq!(received_content.to_cbor_bytes().unwrap(), bytes);
+
+        let derived_values = received.derived_values.unwrap();
+        assert_eq!(&*derived_values.mls_group_id, b"group");
+        assert_eq!(derived_values.sender_leaf_index, 3);
+        assert_eq!(
+            derived_values.message_id,
+            MessageId::construct(
+                derived_values.sender_user_url.as_ref(),
+                derived_values.room_url.as_ref(),
+                &mimi_content
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn derives_values_from_wrapped_status_reports() {
+        let report =
+            MessageStatusReport::from_cbor_bytes(include_bytes!("../tests/examples/report.cbor"))
+                .unwrap();
+        let mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy([1; 16])
+            .topic_id(vec![].into())
+            .nested_part(report.to_nested_part().unwrap())
+            .build();
+        let message = ReceivedMessage {
+            group_id: b"group".to_vec(),
+            sender_leaf_index: 3,
+            application_data: mimi_content.to_mls_application_data().unwrap(),
+        };
+        let context = MlsSenderContext::builder()
+            .hub_accepted_timestamp(Timestamp::MsecsSinceEpoch(1644387225000))
+            .sender_client_url("mimi://example.com/d/alice-smith/phone".into())
+            .sender_user_url("mimi://example.com/u/alice-smith".into())
+            .room_url("mimi://example.com/r/engineering_team".into())
+            .build();
+
+        let received = ReceivedMimiPayload::from_mls_message(&message, context).unwrap();
+        assert_eq!(received.payload.status_report(), Some(&report));
+        let derived_values = received.derived_values.unwrap();
+        assert_eq!(
+            derived_values.message_id,
+            MessageId::construct(
+                derived_values.sender_user_url.as_ref(),
+                derived_values.room_url.as_ref(),
+                &mimi_content
+            )
+            .unwrap()
+        );
+    }
+}
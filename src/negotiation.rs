@@ -0,0 +1,199 @@
+use crate::{
+    BaseDispos, Disposition, MediaType, MimiContent, MimiContentError, MultiPart, NestedPart,
+    NestedPartContent, PartSemantics, SinglePart,
+};
+
+/// Whether `media_type` matches one of the `accepted` media types, which may use wildcards
+/// (see [`MediaType::matches`])
+pub fn is_accepted(accepted: &[MediaType], media_type: &MediaType) -> bool {
+    accepted.iter().any(|pattern| media_type.matches(pattern))
+}
+
+fn is_accepted_content_type(accepted: &[MediaType], content_type: &str) -> bool {
+    MediaType::parse(content_type).is_ok_and(|media_type| is_accepted(accepted, &media_type))
+}
+
+fn not_renderable(reason: impl Into<String>) -> MimiContentError {
+    MimiContentError::NotRenderable(reason.into())
+}
+
+/// Why a part cannot be downgraded
+struct Unrenderable {
+    error: MimiContentError,
+    /// Whether a `SingleUnit` multipart failed, which a `ProcessAll` parent must not drop
+    single_unit: bool,
+}
+
+impl From<MimiContentError> for Unrenderable {
+    fn from(error: MimiContentError) -> Self {
+        Self {
+            error,
+            single_unit: false,
+        }
+    }
+}
+
+impl NestedPart {
+    /// Whether every part that must be processed has an accepted media type.
+    ///
+    /// External parts without a content type are assumed to be renderable, since their
+    /// type is only known once downloaded.
+    pub fn is_renderable_by(&self, accepted: &[MediaType]) -> bool {
+        match &self.part_content {
+            NestedPartContent::NullPart => true,
+            NestedPartContent::SinglePart(single) => {
+                is_accepted_content_type(accepted, &single.content_type)
+            }
+            NestedPartContent::ExternalPart(external) => {
+                external.content_type.is_empty()
+                    || is_accepted_content_type(accepted, &external.content_type)
+            }
+            NestedPartContent::MultiPart(multi) => match multi.part_semantics {
+                PartSemantics::ChooseOne => multi
+                    .parts
+                    .iter()
+                    .any(|part| part.is_renderable_by(accepted)),
+                PartSemantics::SingleUnit | PartSemantics::ProcessAll => multi
+                    .parts
+                    .iter()
+                    .all(|part| part.is_renderable_by(accepted)),
+            },
+            NestedPartContent::Unknown { .. } => false,
+        }
+    }
+
+    /// Transforms the part so that it only contains accepted media types:
+    ///
+    /// - `ChooseOne` multiparts are replaced by their first renderable alternative, which
+    ///   keeps the disposition and language it inherited from them
+    /// - GFM-MIMI parts are converted to `text/plain` (with the `gfm-mimi` feature)
+    /// - `ProcessAll` parts that cannot be rendered are dropped, unless they contain a
+    ///   `SingleUnit` multipart that cannot be rendered
+    ///
+    /// Fails when a `SingleUnit` part, at any depth outside of the alternatives of a
+    /// `ChooseOne`, or the part itself, cannot be rendered. A `ProcessAll` multipart that has
+    /// no renderable part left cannot be rendered either.
+    pub fn downgrade_for(&self, accepted: &[MediaType]) -> Result<Self, MimiContentError> {
+        self.downgrade(accepted)
+            .map_err(|unrenderable| unrenderable.error)
+    }
+
+    fn downgrade(&self, accepted: &[MediaType]) -> Result<Self, Unrenderable> {
+        if self.is_renderable_by(accepted) {
+            return Ok(self.clone());
+        }
+
+        let part_content = match &self.part_content {
+            NestedPartContent::SinglePart(single) => {
+                NestedPartContent::SinglePart(downgrade_single_part(single, accepted)?)
+            }
+            NestedPartContent::MultiPart(multi) => match multi.part_semantics {
+                PartSemantics::ChooseOne => {
+                    let mut chosen = multi
+                        .parts
+                        .iter()
+                        .find_map(|part| part.downgrade(accepted).ok())
+                        .ok_or_else(|| not_renderable("none of the alternatives is accepted"))?;
+                    if chosen.disposition == Disposition::Base(BaseDispos::Unspecified) {
+                        chosen.disposition = self.disposition;
+                    }
+                    if chosen.language.is_empty() {
+                        chosen.language = self.language.clone();
+                    }
+                    return Ok(chosen);
+                }
+                PartSemantics::SingleUnit => NestedPartContent::MultiPart(MultiPart {
+                    part_semantics: PartSemantics::SingleUnit,
+                    parts: multi
+                        .parts
+                        .iter()
+                        .map(|part| part.downgrade(accepted))
+                        .collect::<Result<_, _>>()
+                        .map_err(|unrenderable| Unrenderable {
+                            single_unit: true,
+                            ..unrenderable
+                        })?,
+                }),
+                PartSemantics::ProcessAll => {
+                    let mut parts = vec![];
+                    for part in &multi.parts {
+                        match part.downgrade(accepted) {
+                            Ok(part) => parts.push(part),
+                            Err(unrenderable) if unrenderable.single_unit => {
+                                return Err(unrenderable)
+                            }
+                            Err(_) => {}
+                        }
+                    }
+                    if parts.is_empty() {
+                        return Err(not_renderable("none of the parts is accepted").into());
+                    }
+                    NestedPartContent::MultiPart(MultiPart {
+                        part_semantics: PartSemantics::ProcessAll,
+                        parts,
+                    })
+                }
+            },
+            NestedPartContent::ExternalPart(external) => {
+                return Err(not_renderable(format!(
+                    "external content of type {:?} is not accepted",
+                    &*external.content_type
+                ))
+                .into());
+            }
+            NestedPartContent::Unknown { cardinality, .. } => {
+                return Err(not_renderable(format!("unknown cardinality {cardinality}")).into());
+            }
+            NestedPartContent::NullPart => unreachable!("null parts are always renderable"),
+        };
+
+        Ok(Self {
+            part_content,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(feature = "gfm-mimi")]
+fn downgrade_single_part(
+    single: &SinglePart,
+    accepted: &[MediaType],
+) -> Result<SinglePart, MimiContentError> {
+    let media_type = single.media_type()?;
+    if media_type.matches(&MediaType::TEXT_MARKDOWN_GFM_MIMI)
+        && is_accepted(accepted, &MediaType::TEXT_PLAIN_UTF8)
+    {
+        let markdown = single.text()?;
+        let text = crate::gfm_mimi::GfmMimiRenderer::new().gfm_mimi_to_plain_text(&markdown);
+        return Ok(SinglePart::from_text(MediaType::TEXT_PLAIN, text));
+    }
+    Err(not_renderable(format!("{media_type} is not accepted")))
+}
+
+#[cfg(not(feature = "gfm-mimi"))]
+fn downgrade_single_part(
+    single: &SinglePart,
+    _accepted: &[MediaType],
+) -> Result<SinglePart, MimiContentError> {
+    Err(not_renderable(format!(
+        "{} is not accepted",
+        single.media_type()?
+    )))
+}
+
+impl MimiContent {
+    /// See [`NestedPart::is_renderable_by`]
+    pub fn is_renderable_by(&self, accepted: &[MediaType]) -> bool {
+        self.nested_part.is_renderable_by(accepted)
+    }
+
+    /// Returns a copy of the message that a recipient accepting only `accepted` media types
+    /// can render (see [`NestedPart::downgrade_for`]).
+    ///
+    /// The salt and other fields are kept, but a downgraded message has a different message id.
+    pub fn downgrade_for(&self, accepted: &[MediaType]) -> Result<Self, MimiContentError> {
+        let mut downgraded = self.clone();
+        downgraded.nested_part = self.nested_part.downgrade_for(accepted)?;
+        Ok(downgraded)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{dispositions::Disposition, Bstr, BstrRef, MimiContentAsRef, Tstr, TstrRef};
+use crate::{dispositions::Disposition, Bstr, BstrRef, MimiContentAsRef, Tstr, TstrRef, Value};
 
 mod codec;
 
@@ -40,6 +40,7 @@ impl SinglePartRef<'_> {
 
 impl MimiContentAsRef for SinglePart {
     type Target<'a> = SinglePartRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> SinglePartRef {
         SinglePartRef {
             content_type: self.content_type.as_ref(),
@@ -88,6 +89,7 @@ impl ExternalPartRef<'_> {
 
 impl MimiContentAsRef for ExternalPart {
     type Target<'a> = ExternalPartRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> ExternalPartRef {
         ExternalPartRef {
             content_type: self.content_type.as_ref(),
@@ -115,21 +117,27 @@ pub struct MultiPart {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MultiPartRef<'a> {
     pub part_semantics: &'a PartSemantics,
-    pub parts: Vec<NestedPartRef<'a>>,
+    pub parts: &'a [NestedPart],
 }
 
-impl MultiPartRef<'_> {
+impl<'a> MultiPartRef<'a> {
     pub const fn field_count() -> usize {
         2
     }
+
+    /// Borrows each of the parts, without collecting them
+    pub fn iter_parts(&self) -> impl ExactSizeIterator<Item = NestedPartRef<'a>> + 'a {
+        self.parts.iter().map(NestedPart::as_ref)
+    }
 }
 
 impl MimiContentAsRef for MultiPart {
     type Target<'a> = MultiPartRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> MultiPartRef {
         MultiPartRef {
             part_semantics: &self.part_semantics,
-            parts: self.parts.iter().map(NestedPart::as_ref).collect(),
+            parts: &self.parts,
         }
     }
 }
@@ -146,6 +154,20 @@ pub enum NestedPartContentCardinality {
     MultiPart = 3,
 }
 
+impl TryFrom<u8> for NestedPartContentCardinality {
+    type Error = u8;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::NullPart),
+            1 => Ok(Self::SinglePart),
+            2 => Ok(Self::ExternalPart),
+            3 => Ok(Self::MultiPart),
+            _ => Err(value),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum NestedPartContent {
@@ -154,6 +176,13 @@ pub enum NestedPartContent {
     SinglePart(SinglePart) = NestedPartContentCardinality::SinglePart as u8,
     ExternalPart(ExternalPart) = NestedPartContentCardinality::ExternalPart as u8,
     MultiPart(MultiPart) = NestedPartContentCardinality::MultiPart as u8,
+    /// Content with a cardinality defined by a newer version of the format, kept as decoded
+    /// values so that it can be re-encoded. The encoding only matches the received bytes
+    /// when they use preferred (shortest, definite-length) CBOR encodings.
+    Unknown {
+        cardinality: u8,
+        raw_fields: Vec<Value>,
+    },
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -164,6 +193,10 @@ pub enum NestedPartContentRef<'a> {
     SinglePart(SinglePartRef<'a>) = NestedPartContentCardinality::SinglePart as u8,
     ExternalPart(ExternalPartRef<'a>) = NestedPartContentCardinality::ExternalPart as u8,
     MultiPart(MultiPartRef<'a>) = NestedPartContentCardinality::MultiPart as u8,
+    Unknown {
+        cardinality: &'a u8,
+        raw_fields: &'a [Value],
+    },
 }
 
 impl MimiContentAsRef for NestedPartContent {
@@ -174,28 +207,69 @@ impl MimiContentAsRef for NestedPartContent {
             Self::SinglePart(single) => NestedPartContentRef::SinglePart(single.as_ref()),
             Self::ExternalPart(external) => NestedPartContentRef::ExternalPart(external.as_ref()),
             Self::MultiPart(multi) => NestedPartContentRef::MultiPart(multi.as_ref()),
+            Self::Unknown {
+                cardinality,
+                raw_fields,
+            } => NestedPartContentRef::Unknown {
+                cardinality,
+                raw_fields,
+            },
         }
     }
 }
 
 impl NestedPartContent {
+    /// Unknown content is reported as a [`NestedPartContentCardinality::NullPart`], as the
+    /// receiver can't process any of it. See [`Self::try_cardinality`] to tell them apart.
     pub fn cardinality(&self) -> NestedPartContentCardinality {
+        self.try_cardinality()
+            .unwrap_or(NestedPartContentCardinality::NullPart)
+    }
+
+    /// Returns `None` for [`Self::Unknown`] content
+    pub fn try_cardinality(&self) -> Option<NestedPartContentCardinality> {
+        match self {
+            Self::NullPart => Some(NestedPartContentCardinality::NullPart),
+            Self::SinglePart(_) => Some(NestedPartContentCardinality::SinglePart),
+            Self::ExternalPart(_) => Some(NestedPartContentCardinality::ExternalPart),
+            Self::MultiPart(_) => Some(NestedPartContentCardinality::MultiPart),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    /// The cardinality as it appears on the wire
+    pub fn raw_cardinality(&self) -> u8 {
         match self {
-            Self::NullPart => NestedPartContentCardinality::NullPart,
-            Self::SinglePart(_) => NestedPartContentCardinality::SinglePart,
-            Self::ExternalPart(_) => NestedPartContentCardinality::ExternalPart,
-            Self::MultiPart(_) => NestedPartContentCardinality::MultiPart,
+            Self::Unknown { cardinality, .. } => *cardinality,
+            known => known.cardinality() as u8,
         }
     }
 }
 
 impl NestedPartContentRef<'_> {
+    /// Unknown content is reported as a [`NestedPartContentCardinality::NullPart`], as the
+    /// receiver can't process any of it. See [`Self::try_cardinality`] to tell them apart.
     pub fn cardinality(&self) -> NestedPartContentCardinality {
+        self.try_cardinality()
+            .unwrap_or(NestedPartContentCardinality::NullPart)
+    }
+
+    /// Returns `None` for [`Self::Unknown`] content
+    pub fn try_cardinality(&self) -> Option<NestedPartContentCardinality> {
         match self {
-            Self::NullPart => NestedPartContentCardinality::NullPart,
-            Self::SinglePart(_) => NestedPartContentCardinality::SinglePart,
-            Self::ExternalPart(_) => NestedPartContentCardinality::ExternalPart,
-            Self::MultiPart(_) => NestedPartContentCardinality::MultiPart,
+            Self::NullPart => Some(NestedPartContentCardinality::NullPart),
+            Self::SinglePart(_) => Some(NestedPartContentCardinality::SinglePart),
+            Self::ExternalPart(_) => Some(NestedPartContentCardinality::ExternalPart),
+            Self::MultiPart(_) => Some(NestedPartContentCardinality::MultiPart),
+            Self::Unknown { .. } => None,
+        }
+    }
+
+    /// The cardinality as it appears on the wire
+    pub fn raw_cardinality(&self) -> u8 {
+        match self {
+            Self::Unknown { cardinality, .. } => **cardinality,
+            known => known.cardinality() as u8,
         }
     }
 }
@@ -207,6 +281,10 @@ pub struct NestedPart {
     #[builder(default)]
     pub language: Tstr,
     pub part_content: NestedPartContent,
+    /// Trailing fields defined by a newer version of the format, re-encoded with preferred
+    /// CBOR encodings
+    #[builder(default)]
+    pub unknown_fields: Vec<Value>,
 }
 
 impl Default for NestedPart {
@@ -215,17 +293,20 @@ impl Default for NestedPart {
             disposition: Default::default(),
             language: Tstr::from("en".to_owned()),
             part_content: Default::default(),
+            unknown_fields: Default::default(),
         }
     }
 }
 
 impl MimiContentAsRef for NestedPart {
     type Target<'a> = NestedPartRef<'a>;
+    #[allow(mismatched_lifetime_syntaxes)]
     fn as_ref(&self) -> NestedPartRef {
         NestedPartRef {
             disposition: &self.disposition,
             language: TstrRef::from(&*self.language),
             part_content: self.part_content.as_ref(),
+            unknown_fields: &self.unknown_fields,
         }
     }
 }
@@ -235,6 +316,7 @@ pub struct NestedPartRef<'a> {
     pub disposition: &'a Disposition,
     pub language: TstrRef<'a>,
     pub part_content: NestedPartContentRef<'a>,
+    pub unknown_fields: &'a [Value],
 }
 
 impl NestedPartRef<'_> {
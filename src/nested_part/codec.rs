@@ -11,8 +11,9 @@ fn map_len_for_nestedpartref(nested_part: &NestedPartRef<'_>) -> usize {
         NestedPartContentRef::SinglePart(_) => SinglePartRef::field_count(),
         NestedPartContentRef::MultiPart(_) => MultiPartRef::field_count(),
         NestedPartContentRef::ExternalPart(_) => ExternalPartRef::field_count(),
+        NestedPartContentRef::Unknown { raw_fields, .. } => raw_fields.len(),
     };
-    NestedPartRef::field_count() + extra_fields
+    NestedPartRef::field_count() + extra_fields + nested_part.unknown_fields.len()
 }
 
 impl serde::Serialize for NestedPartRef<'_> {
@@ -23,7 +24,7 @@ impl serde::Serialize for NestedPartRef<'_> {
         let mut seq = serializer.serialize_seq(Some(map_len_for_nestedpartref(self)))?;
         seq.serialize_element(&self.disposition)?;
         seq.serialize_element(&self.language)?;
-        seq.serialize_element(&self.part_content.cardinality())?;
+        seq.serialize_element(&self.part_content.raw_cardinality())?;
         match &self.part_content {
             NestedPartContentRef::NullPart => {}
             NestedPartContentRef::SinglePart(single) => {
@@ -48,6 +49,14 @@ impl serde::Serialize for NestedPartRef<'_> {
                 seq.serialize_element(&multi.part_semantics)?;
                 seq.serialize_element(&multi.parts)?;
             }
+            NestedPartContentRef::Unknown { raw_fields, .. } => {
+                for raw_field in *raw_fields {
+                    seq.serialize_element(raw_field)?;
+                }
+            }
+        }
+        for unknown_field in self.unknown_fields {
+            seq.serialize_element(unknown_field)?;
         }
 
         seq.end()
@@ -85,14 +94,19 @@ impl<'de> serde::de::Visitor<'de> for NestedPartVisitor {
             .next_element()?
             .ok_or_else(|| serde::de::Error::invalid_length(counter, &self))?;
         counter += 1;
-        let cardinality: NestedPartContentCardinality = seq
+        let raw_cardinality: u8 = seq
             .next_element()?
             .ok_or_else(|| serde::de::Error::invalid_length(counter, &self))?;
         counter += 1;
 
-        let part_content = match cardinality {
-            NestedPartContentCardinality::NullPart => NestedPartContent::NullPart,
-            NestedPartContentCardinality::SinglePart => {
+        let part_content = match NestedPartContentCardinality::try_from(raw_cardinality) {
+            Err(cardinality) => NestedPartContent::Unknown {
+                cardinality,
+                raw_fields: std::iter::from_fn(|| seq.next_element().transpose())
+                    .collect::<Result<_, _>>()?,
+            },
+            Ok(NestedPartContentCardinality::NullPart) => NestedPartContent::NullPart,
+            Ok(NestedPartContentCardinality::SinglePart) => {
                 let content_type = seq
                     .next_element()?
                     .ok_or_else(|| serde::de::Error::invalid_length(counter, &self))?;
@@ -106,7 +120,7 @@ impl<'de> serde::de::Visitor<'de> for NestedPartVisitor {
                     content,
                 })
             }
-            NestedPartContentCardinality::ExternalPart => {
+            Ok(NestedPartContentCardinality::ExternalPart) => {
                 let content_type = seq
                     .next_element()?
                     .ok_or_else(|| serde::de::Error::invalid_length(counter, &self))?;
@@ -167,7 +181,7 @@ impl<'de> serde::de::Visitor<'de> for NestedPartVisitor {
                     filename,
                 })
             }
-            NestedPartContentCardinality::MultiPart => {
+            Ok(NestedPartContentCardinality::MultiPart) => {
                 let part_semantics = seq
                     .next_element()?
                     .ok_or_else(|| serde::de::Error::invalid_length(counter, &self))?;
@@ -184,10 +198,14 @@ impl<'de> serde::de::Visitor<'de> for NestedPartVisitor {
             }
         };
 
+        let unknown_fields =
+            std::iter::from_fn(|| seq.next_element().transpose()).collect::<Result<_, _>>()?;
+
         Ok(NestedPart {
             disposition,
             language,
             part_content,
+            unknown_fields,
         })
     }
 }
@@ -0,0 +1,171 @@
+use crate::{
+    delivery_report::MessageStatusReport, Bstr, MediaType, MimiContent,
+    MimiContentDeserialize as _, MimiContentError, MimiContentSerialize as _, NestedPart,
+    NestedPartContent, SinglePart, MIMI_CONTENT_MESSAGE_STATUS_MIME, MIMI_CONTENT_MIME,
+};
+
+fn is_media_type(content_type: &str, expected: &MediaType) -> bool {
+    MediaType::parse(content_type).is_ok_and(|media_type| media_type.same_essence(expected))
+}
+
+/// A decoded MIMI application payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MimiPayload {
+    /// An `application/mimi-content` message
+    Content(Box<MimiContent>),
+    /// An `application/mimi-message-status` report sent on its own
+    StatusReport(MessageStatusReport),
+    /// A [`MimiContent`] whose nested part is a status report, kept whole so its message id
+    /// and envelope can still be checked
+    WrappedStatusReport {
+        content: Box<MimiContent>,
+        report: MessageStatusReport,
+    },
+}
+
+impl MimiPayload {
+    /// Decodes `bytes` according to `content_type`.
+    ///
+    /// The report of a [`MimiContent`] whose nested part is a status report is decoded too,
+    /// see [`Self::status_report`].
+    pub fn decode(content_type: &str, bytes: &[u8]) -> Result<Self, MimiContentError> {
+        if is_media_type(content_type, &MediaType::APPLICATION_MIMI_MESSAGE_STATUS) {
+            return Ok(Self::StatusReport(MessageStatusReport::from_cbor_bytes(
+                bytes,
+            )?));
+        }
+
+        if !is_media_type(content_type, &MediaType::APPLICATION_MIMI_CONTENT) {
+            return Err(MimiContentError::UnexpectedContentType(
+                content_type.to_string(),
+            ));
+        }
+
+        let mimi_content = MimiContent::from_cbor_bytes(bytes)?;
+        match mimi_content.status_report() {
+            Some(report) => Ok(Self::WrappedStatusReport {
+                report: report?,
+                content: Box::new(mimi_content),
+            }),
+            None => Ok(Self::Content(Box::new(mimi_content))),
+        }
+    }
+
+    /// The message, unless this is a standalone status report
+    pub fn mimi_content(&self) -> Option<&MimiContent> {
+        match self {
+            Self::Content(content) | Self::WrappedStatusReport { content, .. } => Some(content),
+            Self::StatusReport(_) => None,
+        }
+    }
+
+    /// The status report, whether sent on its own or wrapped in a [`MimiContent`], so read
+    /// receipts are surfaced the same way regardless of how they were sent
+    pub fn status_report(&self) -> Option<&MessageStatusReport> {
+        match self {
+            Self::StatusReport(report) | Self::WrappedStatusReport { report, .. } => Some(report),
+            Self::Content(_) => None,
+        }
+    }
+
+    /// The content type to use when sending [`Self::to_cbor_bytes`] on its own
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Content(_) | Self::WrappedStatusReport { .. } => MIMI_CONTENT_MIME,
+            Self::StatusReport(_) => MIMI_CONTENT_MESSAGE_STATUS_MIME,
+        }
+    }
+
+    pub fn to_cbor_bytes(&self) -> Result<Vec<u8>, MimiContentError> {
+        match self {
+            Self::Content(content) | Self::WrappedStatusReport { content, .. } => {
+                content.to_cbor_bytes()
+            }
+            Self::StatusReport(report) => report.to_cbor_bytes(),
+        }
+    }
+}
+
+impl MessageStatusReport {
+    /// Wraps the report in a [`NestedPart`] of type `application/mimi-message-status`,
+    /// ready to be used as the nested part of a [`MimiContent`]
+    pub fn to_nested_part(&self) -> Result<NestedPart, MimiContentError> {
+        Ok(NestedPart::builder()
+            .part_content(NestedPartContent::SinglePart(SinglePart {
+                content_type: MediaType::APPLICATION_MIMI_MESSAGE_STATUS.into(),
+                content: Bstr::from(self.to_cbor_bytes()?),
+            }))
+            .build())
+    }
+
+    /// Decodes the report carried by `nested_part`, returning `None` if it isn't
+    /// an `application/mimi-message-status` part
+    pub fn from_nested_part(nested_part: &NestedPart) -> Option<Result<Self, MimiContentError>> {
+        match &nested_part.part_content {
+            NestedPartContent::SinglePart(single)
+                if is_media_type(
+                    &single.content_type,
+                    &MediaType::APPLICATION_MIMI_MESSAGE_STATUS,
+                ) =>
+            {
+                Some(Self::from_cbor_bytes(&single.content))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl MimiContent {
+    /// Decodes the status report carried by this message, if its nested part is one
+    pub fn status_report(&self) -> Option<Result<MessageStatusReport, MimiContentError>> {
+        MessageStatusReport::from_nested_part(&self.nested_part)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        delivery_report::MessageStatusReport, MimiContent, MimiContentDeserialize as _,
+        MimiContentSerialize as _, MIMI_CONTENT_MESSAGE_STATUS_MIME, MIMI_CONTENT_MIME,
+    };
+
+    use super::MimiPayload;
+
+    fn report() -> MessageStatusReport {
+        MessageStatusReport::from_cbor_bytes(include_bytes!("../tests/examples/report.cbor"))
+            .unwrap()
+    }
+
+    #[test]
+    fn standalone_report_is_decoded() {
+        let bytes = report().to_cbor_bytes().unwrap();
+        let payload = MimiPayload::decode("Application/Mimi-Message-Status", &bytes).unwrap();
+        assert_eq!(payload, MimiPayload::StatusReport(report()));
+        assert_eq!(payload.content_type(), MIMI_CONTENT_MESSAGE_STATUS_MIME);
+    }
+
+    #[test]
+    fn wrapped_report_keeps_its_envelope() {
+        let mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy([1; 16])
+            .topic_id(vec![].into())
+            .nested_part(report().to_nested_part().unwrap())
+            .build();
+
+        let bytes = mimi_content.to_cbor_bytes().unwrap();
+        let payload = MimiPayload::decode(MIMI_CONTENT_MIME, &bytes).unwrap();
+        assert_eq!(payload.status_report(), Some(&report()));
+        assert_eq!(payload.mimi_content(), Some(&mimi_content));
+        assert_eq!(payload.content_type(), MIMI_CONTENT_MIME);
+        assert_eq!(payload.to_cbor_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn regular_content_is_kept() {
+        let bytes = include_bytes!("../tests/examples/original.cbor");
+        let payload = MimiPayload::decode(MIMI_CONTENT_MIME, bytes).unwrap();
+        assert!(matches!(payload, MimiPayload::Content(_)));
+        assert_eq!(payload.to_cbor_bytes().unwrap(), bytes);
+        assert!(MimiPayload::decode("text/plain", bytes).is_err());
+    }
+}
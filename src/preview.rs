@@ -0,0 +1,461 @@
+//! Link previews, sent as parts with the `preview` disposition next to the text they preview
+//!
+//! A preview is a `ProcessAll` multipart whose first part is a `text/html` document made only
+//! of [OpenGraph](https://ogp.me/) `<meta>` tags, optionally followed by a thumbnail:
+//!
+//! ```text
+//! ProcessAll multipart
+//! ├── text/markdown;variant=GFM-MIMI    the message
+//! └── ProcessAll multipart (preview)
+//!     ├── text/html;charset=utf-8       <meta property="og:url" content="…">…
+//!     └── image/* or external part      the thumbnail
+//! ```
+
+use crate::{
+    safety::UrlPolicy, BaseDispos, Disposition, ExternalPart, MediaType, MimiContent,
+    MimiContentError, MultiPart, NestedPart, NestedPartContent, PartInfo, PartSemantics,
+    SinglePart, Walk,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Thumbnail {
+    Inline(SinglePart),
+    External(ExternalPart),
+}
+
+/// A preview of the page at `url`. Empty strings are absent values.
+#[derive(Debug, Clone, PartialEq, Eq, bon::Builder)]
+pub struct LinkPreview {
+    #[builder(into)]
+    pub url: String,
+    #[builder(into, default)]
+    pub title: String,
+    #[builder(into, default)]
+    pub description: String,
+    #[builder(into, default)]
+    pub site_name: String,
+    pub thumbnail: Option<Thumbnail>,
+}
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidLinkPreview(reason)
+}
+
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '"' => escaped.push_str("&quot;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .map(|end| &rest[1..end + 1])
+            .filter(|entity| entity.len() <= 10);
+        let decoded = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "nbsp" => Some('\u{A0}'),
+            _ => {
+                let code = match entity.strip_prefix('#')? {
+                    hex if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16),
+                    decimal => decimal.parse(),
+                };
+                char::from_u32(code.ok()?)
+            }
+        });
+        match (entity, decoded) {
+            (Some(entity), Some(decoded)) => {
+                unescaped.push(decoded);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Position of `needle` in `haystack`, ignoring ASCII case
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Parses the attributes of a tag, from after its name to its closing `>`
+fn parse_attributes(mut tag: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    loop {
+        tag = tag.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        let name_end = tag
+            .find(|c: char| c.is_ascii_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(tag.len());
+        if name_end == 0 {
+            return attributes;
+        }
+        let name = tag[..name_end].to_ascii_lowercase();
+        tag = tag[name_end..].trim_start();
+        let value = match tag.strip_prefix('=') {
+            Some(rest) => {
+                let rest = rest.trim_start();
+                let (value, rest) = match rest.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let end = rest[1..].find(quote).map_or(rest.len(), |end| end + 1);
+                        (&rest[1..end], rest.get(end + 1..).unwrap_or_default())
+                    }
+                    _ => {
+                        let end = rest
+                            .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                            .unwrap_or(rest.len());
+                        rest.split_at(end)
+                    }
+                };
+                tag = rest;
+                unescape(value)
+            }
+            None => String::new(),
+        };
+        attributes.push((name, value));
+    }
+}
+
+/// The `property` or `name`, and `content`, of each `<meta>` tag of `html`
+fn meta_tags(html: &str) -> Vec<(String, String)> {
+    let mut tags = vec![];
+    let mut rest = html;
+    while let Some(start) = find_ignore_case(rest, "<meta") {
+        rest = &rest[start + "<meta".len()..];
+        let end = rest.find('>').unwrap_or(rest.len());
+        let attributes = parse_attributes(&rest[..end]);
+        rest = &rest[end..];
+
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.clone())
+        };
+        if let (Some(key), Some(content)) = (
+            attribute("property").or_else(|| attribute("name")),
+            attribute("content"),
+        ) {
+            tags.push((key.to_ascii_lowercase(), content));
+        }
+    }
+    tags
+}
+
+fn title_tag(html: &str) -> Option<String> {
+    let start = find_ignore_case(html, "<title")?;
+    let rest = &html[start..];
+    let rest = &rest[rest.find('>')? + 1..];
+    let end = find_ignore_case(rest, "</title").unwrap_or(rest.len());
+    Some(unescape(rest[..end].trim()))
+}
+
+/// The trimmed content of the first non-blank `<meta>` tag for `key`
+fn non_empty_tag(tags: &[(String, String)], key: &str) -> Option<String> {
+    tags.iter()
+        .find(|(tag, content)| tag == key && !content.trim().is_empty())
+        .map(|(_, content)| content.trim().to_string())
+}
+
+impl LinkPreview {
+    /// Builds a preview of the page at `url` from its HTML, using OpenGraph properties and
+    /// falling back to the `<title>` and the `description` meta tag. The page's own `og:url` is
+    /// ignored. There is no thumbnail, see [`Self::image_url`].
+    pub fn from_html(url: &str, html: &str) -> Self {
+        let tags = meta_tags(html);
+        let tag = |key: &str| non_empty_tag(&tags, key);
+        Self {
+            url: url.to_string(),
+            title: tag("og:title")
+                .or_else(|| title_tag(html))
+                .unwrap_or_default(),
+            description: tag("og:description")
+                .or_else(|| tag("description"))
+                .unwrap_or_default(),
+            site_name: tag("og:site_name").unwrap_or_default(),
+            thumbnail: None,
+        }
+    }
+
+    /// The `og:image` of the page at `url`, if it passes `policy`. The sender fetches it, and
+    /// sets it as an inline thumbnail or uploads it with [`ExternalPart::upload`]: recipients
+    /// don't fetch third-party URLs.
+    pub fn image_url(url: &str, html: &str, policy: &UrlPolicy) -> Option<url::Url> {
+        let image = non_empty_tag(&meta_tags(html), "og:image")?;
+        let image = url::Url::parse(url)
+            .and_then(|base| base.join(&image))
+            .map_or(image, String::from);
+        policy.check(&image).ok()
+    }
+
+    /// The OpenGraph `<meta>` tags sent in the preview part
+    pub fn to_html(&self) -> String {
+        [
+            ("og:url", &self.url),
+            ("og:title", &self.title),
+            ("og:description", &self.description),
+            ("og:site_name", &self.site_name),
+        ]
+        .into_iter()
+        .filter(|(_, content)| !content.is_empty())
+        .map(|(property, content)| {
+            format!(
+                "<meta property=\"{property}\" content=\"{}\">\n",
+                escape_attribute(content)
+            )
+        })
+        .collect()
+    }
+
+    /// The part to send, with the `preview` disposition
+    pub fn to_nested_part(&self) -> NestedPart {
+        let mut parts = vec![NestedPart::builder()
+            .part_content(NestedPartContent::SinglePart(SinglePart::from_text(
+                MediaType::TEXT_HTML,
+                self.to_html(),
+            )))
+            .build()];
+        if let Some(thumbnail) = &self.thumbnail {
+            let part_content = match thumbnail {
+                Thumbnail::Inline(single) => NestedPartContent::SinglePart(single.clone()),
+                Thumbnail::External(external) => NestedPartContent::ExternalPart(external.clone()),
+            };
+            parts.push(NestedPart::builder().part_content(part_content).build());
+        }
+        NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Preview))
+            .part_content(NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                parts,
+            }))
+            .build()
+    }
+
+    /// Reads a preview part, as produced by [`Self::to_nested_part`] (its disposition isn't
+    /// checked). A lone `text/html` part is a preview without a thumbnail.
+    pub fn from_nested_part(part: &NestedPart) -> Result<Self, MimiContentError> {
+        let (html, thumbnail) = match &part.part_content {
+            NestedPartContent::MultiPart(multi) => match multi.parts.as_slice() {
+                [html] => (html, None),
+                [html, thumbnail] => (html, Some(thumbnail)),
+                _ => return Err(invalid("expected an HTML part and an optional thumbnail")),
+            },
+            _ => (part, None),
+        };
+
+        let NestedPartContent::SinglePart(html) = &html.part_content else {
+            return Err(invalid("the preview is not a single part"));
+        };
+        if !html.media_type()?.same_essence(&MediaType::TEXT_HTML) {
+            return Err(invalid("the preview is not HTML"));
+        }
+        let html = html.text()?;
+
+        let thumbnail = match thumbnail.map(|thumbnail| &thumbnail.part_content) {
+            None => None,
+            Some(NestedPartContent::SinglePart(single)) => Some(Thumbnail::Inline(single.clone())),
+            Some(NestedPartContent::ExternalPart(external)) => {
+                Some(Thumbnail::External(external.clone()))
+            }
+            Some(_) => return Err(invalid("the thumbnail is not a single or external part")),
+        };
+
+        let tags = meta_tags(&html);
+        let tag = |key: &str| {
+            tags.iter()
+                .find(|(tag, _)| tag == key)
+                .map(|(_, content)| content.clone())
+                .unwrap_or_default()
+        };
+        let url = tag("og:url");
+        if url.is_empty() {
+            return Err(invalid("the preview has no URL"));
+        }
+        Ok(Self {
+            url,
+            title: tag("og:title"),
+            description: tag("og:description"),
+            site_name: tag("og:site_name"),
+            thumbnail,
+        })
+    }
+}
+
+impl NestedPart {
+    /// Adds the preview to a `ProcessAll` multipart, or wraps `self` and the preview in one
+    pub fn with_link_preview(self, preview: &LinkPreview) -> Self {
+        match self.part_content {
+            NestedPartContent::MultiPart(MultiPart {
+                part_semantics: PartSemantics::ProcessAll,
+                mut parts,
+            }) => {
+                parts.push(preview.to_nested_part());
+                Self {
+                    part_content: NestedPartContent::MultiPart(MultiPart {
+                        part_semantics: PartSemantics::ProcessAll,
+                        parts,
+                    }),
+                    ..self
+                }
+            }
+            part_content => Self::builder()
+                .part_content(NestedPartContent::MultiPart(MultiPart {
+                    part_semantics: PartSemantics::ProcessAll,
+                    parts: vec![
+                        Self {
+                            part_content,
+                            ..self
+                        },
+                        preview.to_nested_part(),
+                    ],
+                }))
+                .build(),
+        }
+    }
+}
+
+impl MimiContent {
+    /// See [`NestedPart::with_link_preview`]
+    pub fn attach_link_preview(&mut self, preview: &LinkPreview) {
+        let body = std::mem::take(&mut self.nested_part);
+        self.nested_part = body.with_link_preview(preview);
+    }
+
+    /// The valid previews among the parts with the `preview` disposition
+    pub fn link_previews(&self) -> Vec<LinkPreview> {
+        let mut previews = vec![];
+        self.accept(&mut |part: &NestedPart, info: &PartInfo<'_>| {
+            if info.disposition != Disposition::Base(BaseDispos::Preview) {
+                return Walk::Continue;
+            }
+            previews.extend(LinkPreview::from_nested_part(part).ok());
+            Walk::SkipChildren
+        });
+        previews
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><head>
+  <title>Ignored &amp; unused</title>
+  <meta charset="utf-8">
+  <meta property="og:url" content="https://evil.example/">
+  <META property="og:title" content="Release 2.0 &quot;Aurora&quot;">
+  <meta property='og:site_name' content='Example'>
+  <meta name=description content="What&#39;s new in 2.0">
+  <meta property="og:image" content="/img/aurora.png">
+  <meta property="og:image:type" content="image/png">
+</head><body></body></html>"#;
+
+    #[test]
+    fn builds_previews_from_html() {
+        let preview = LinkPreview::from_html("https://example.com/blog/2.0", PAGE);
+        assert_eq!(preview.url, "https://example.com/blog/2.0");
+        assert_eq!(preview.title, "Release 2.0 \"Aurora\"");
+        assert_eq!(preview.description, "What's new in 2.0");
+        assert_eq!(preview.site_name, "Example");
+        assert_eq!(preview.thumbnail, None);
+
+        let untitled = LinkPreview::from_html("https://example.com", "<title> Home </title>");
+        assert_eq!(untitled.title, "Home");
+    }
+
+    #[test]
+    fn finds_safe_images() {
+        let policy = UrlPolicy::default();
+        assert_eq!(
+            LinkPreview::image_url("https://example.com/blog/2.0", PAGE, &policy).map(String::from),
+            Some("https://example.com/img/aurora.png".to_string())
+        );
+        assert_eq!(
+            LinkPreview::image_url("https://example.com", "<title>Home</title>", &policy),
+            None
+        );
+
+        for image in [
+            "http://127.0.0.1/a.png",
+            "javascript:alert(1)",
+            "//192.168.0.1/a.png",
+        ] {
+            let page = format!(r#"<meta property="og:image" content="{image}">"#);
+            assert_eq!(
+                LinkPreview::image_url("https://example.com", &page, &policy),
+                None,
+                "{image}"
+            );
+        }
+    }
+
+    #[test]
+    fn attaches_and_extracts_previews() {
+        let preview = LinkPreview::builder()
+            .url("https://example.com/?a=1&b=2")
+            .title("<Example>")
+            .thumbnail(Thumbnail::Inline(SinglePart {
+                content_type: "image/png".into(),
+                content: b"\x89PNG\r\n\x1a\n".to_vec().into(),
+            }))
+            .build();
+
+        let mut mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy(Default::default())
+            .topic_id(Default::default())
+            .nested_part(
+                NestedPart::builder()
+                    .part_content(NestedPartContent::SinglePart(SinglePart::from_text(
+                        MediaType::TEXT_PLAIN,
+                        "see https://example.com/?a=1&b=2",
+                    )))
+                    .build(),
+            )
+            .build();
+        mimi_content.attach_link_preview(&preview);
+
+        let NestedPartContent::MultiPart(multi) = &mimi_content.nested_part.part_content else {
+            panic!("expected a multipart");
+        };
+        assert_eq!(multi.part_semantics, PartSemantics::ProcessAll);
+        assert_eq!(multi.parts.len(), 2);
+        assert_eq!(mimi_content.link_previews(), vec![preview.clone()]);
+
+        let body = mimi_content.nested_part.clone().with_link_preview(&preview);
+        assert_eq!(body.iter_parts().count(), 8);
+
+        let not_a_preview = NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Preview))
+            .part_content(NestedPartContent::SinglePart(SinglePart::from_text(
+                MediaType::TEXT_PLAIN,
+                "hi",
+            )))
+            .build();
+        assert!(LinkPreview::from_nested_part(&not_a_preview).is_err());
+    }
+}
@@ -0,0 +1,895 @@
+//! Contact cards, sent as parts with the `profile` disposition, as a `text/vcard`
+//! ([RFC6350](https://www.rfc-editor.org/rfc/rfc6350.html), version 4.0) or an
+//! `application/vcard+json` ([RFC7095](https://www.rfc-editor.org/rfc/rfc7095.html) jCard)
+//!
+//! The common properties are typed, the others are kept verbatim in
+//! [`ContactCard::other_properties`].
+
+use serde_json::Value;
+
+use crate::{
+    media_type::media_type, BaseDispos, Disposition, MediaType, MimiContent, MimiContentError,
+    NestedPart, NestedPartContent, PartInfo, SinglePart, Walk,
+};
+
+const TEXT_VCARD: MediaType = media_type("text", "vcard", &[]);
+const APPLICATION_VCARD_JSON: MediaType = media_type("application", "vcard+json", &[]);
+
+/// Longest line of a vCard, in octets, before folding
+const MAX_LINE_LEN: usize = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    VCard,
+    JCard,
+}
+
+/// The components of the `N` property
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ContactName {
+    pub family: String,
+    pub given: String,
+    pub additional: String,
+    pub prefixes: String,
+    pub suffixes: String,
+}
+
+impl ContactName {
+    fn components(&self) -> [&str; 5] {
+        [
+            &self.family,
+            &self.given,
+            &self.additional,
+            &self.prefixes,
+            &self.suffixes,
+        ]
+    }
+
+    fn from_components(components: &[String]) -> Self {
+        let component = |index: usize| components.get(index).cloned().unwrap_or_default();
+        Self {
+            family: component(0),
+            given: component(1),
+            additional: component(2),
+            prefixes: component(3),
+            suffixes: component(4),
+        }
+    }
+}
+
+/// A value with its `TYPE` parameters, like `work` or `cell`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypedValue {
+    pub value: String,
+    pub types: Vec<String>,
+}
+
+impl From<&str> for TypedValue {
+    fn from(value: &str) -> Self {
+        Self {
+            value: value.to_string(),
+            types: vec![],
+        }
+    }
+}
+
+/// A property without a typed field, with its raw (still escaped) vCard value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VcardProperty {
+    /// Uppercase
+    pub name: String,
+    /// Lowercase names
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+/// A contact card. Empty strings are absent values.
+#[derive(Debug, Clone, PartialEq, Eq, bon::Builder)]
+pub struct ContactCard {
+    /// `FN`, required
+    #[builder(into)]
+    pub formatted_name: String,
+    pub name: Option<ContactName>,
+    #[builder(into, default)]
+    pub nickname: String,
+    #[builder(into, default)]
+    pub org: String,
+    #[builder(into, default)]
+    pub title: String,
+    #[builder(default)]
+    pub emails: Vec<TypedValue>,
+    /// `TEL`, as `tel:` URIs or free text
+    #[builder(default)]
+    pub phones: Vec<TypedValue>,
+    /// Instant messaging URIs, like MIMI user URIs
+    #[builder(default)]
+    pub impps: Vec<TypedValue>,
+    #[builder(default)]
+    pub urls: Vec<TypedValue>,
+    #[builder(into, default)]
+    pub photo: String,
+    #[builder(into, default)]
+    pub note: String,
+    #[builder(into, default)]
+    pub uid: String,
+    #[builder(default)]
+    pub other_properties: Vec<VcardProperty>,
+}
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidContactCard(reason)
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Splits a structured value on the `;` that aren't escaped
+fn split_components(value: &str) -> Vec<String> {
+    let mut components = vec![];
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                components.push(unescape(&value[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    components.push(unescape(&value[start..]));
+    components
+}
+
+/// Encodes a parameter value as in [RFC6868](https://www.rfc-editor.org/rfc/rfc6868.html)
+fn caret_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '^' => encoded.push_str("^^"),
+            '"' => encoded.push_str("^'"),
+            '\n' => encoded.push_str("^n"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+fn caret_decode(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escaped = match (c, chars.peek()) {
+            ('^', Some('^')) => '^',
+            ('^', Some('\'')) => '"',
+            ('^', Some('n')) => '\n',
+            _ => {
+                decoded.push(c);
+                continue;
+            }
+        };
+        chars.next();
+        decoded.push(escaped);
+    }
+    decoded
+}
+
+/// Property and parameter names, and types: `1*(ALPHA / DIGIT / "-")`
+fn is_token(text: &str) -> bool {
+    !text.is_empty()
+        && text
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+}
+
+/// Checks that a value has no control characters, except tabs and the line breaks of
+/// `escaped` values
+fn check_value(value: &str, escaped: bool) -> Result<(), MimiContentError> {
+    if value
+        .chars()
+        .any(|c| c.is_control() && c != '\t' && !(escaped && c == '\n'))
+    {
+        return Err(invalid("control character in a value"));
+    }
+    Ok(())
+}
+
+/// Appends a content line, folded after [`MAX_LINE_LEN`] octets
+fn push_line(out: &mut String, line: &str) {
+    let mut rest = line;
+    let mut limit = MAX_LINE_LEN;
+    while rest.len() > limit {
+        let mut end = limit;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        out.push_str(&rest[..end]);
+        out.push_str("\r\n ");
+        rest = &rest[end..];
+        // the leading space of continuation lines counts
+        limit = MAX_LINE_LEN - 1;
+    }
+    out.push_str(rest);
+    out.push_str("\r\n");
+}
+
+/// A content line: `[group "."] name *(";" param) ":" value`
+struct ContentLine {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+fn parse_content_line(line: &str) -> Result<ContentLine, MimiContentError> {
+    // the value starts at the first colon outside of a quoted parameter value
+    let mut quoted = false;
+    let colon = line
+        .char_indices()
+        .find(|&(_, c)| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ':' && !quoted
+        })
+        .map(|(i, _)| i)
+        .ok_or_else(|| invalid("content line without a value"))?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+
+    let mut params = vec![];
+    let mut quoted = false;
+    let mut parts = vec![];
+    let mut start = 0;
+    for (i, c) in head.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&head[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&head[start..]);
+
+    let name = parts[0];
+    let name = name.rsplit_once('.').map_or(name, |(_group, name)| name);
+    if name.is_empty() {
+        return Err(invalid("content line without a name"));
+    }
+    for param in &parts[1..] {
+        let (param_name, param_value) = param.split_once('=').unwrap_or((param, ""));
+        params.push((
+            param_name.to_ascii_lowercase(),
+            caret_decode(param_value.trim_matches('"')),
+        ));
+    }
+    Ok(ContentLine {
+        name: name.to_ascii_uppercase(),
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn types_of(params: &[(String, String)]) -> Vec<String> {
+    params
+        .iter()
+        .filter(|(name, _)| name == "type")
+        .flat_map(|(_, value)| value.split(','))
+        .filter(|value| !value.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+impl ContactCard {
+    /// Checks the properties that RFC6350 requires, that typed values aren't empty, that names
+    /// and types are tokens, and that values have no control characters but the line breaks of
+    /// text values
+    pub fn validate(&self) -> Result<(), MimiContentError> {
+        if self.formatted_name.trim().is_empty() {
+            return Err(invalid("missing formatted name (FN)"));
+        }
+        let values = [&self.emails, &self.phones, &self.impps, &self.urls];
+        if values
+            .iter()
+            .any(|values| values.iter().any(|value| value.value.trim().is_empty()))
+        {
+            return Err(invalid("empty email, phone, IMPP or URL"));
+        }
+        if values.iter().any(|values| {
+            values
+                .iter()
+                .flat_map(|value| &value.types)
+                .any(|t| !is_token(t))
+        }) {
+            return Err(invalid("a type is not a token"));
+        }
+        for (_, _, value, is_text) in self.properties() {
+            check_value(&value, is_text)?;
+        }
+        for component in self.name.iter().flat_map(ContactName::components) {
+            check_value(component, true)?;
+        }
+        for property in &self.other_properties {
+            if !is_token(&property.name)
+                || ["BEGIN", "END", "VERSION"]
+                    .iter()
+                    .any(|name| property.name.eq_ignore_ascii_case(name))
+            {
+                return Err(invalid("a property name is not a token"));
+            }
+            for (name, value) in &property.params {
+                if !is_token(name) {
+                    return Err(invalid("a parameter name is not a token"));
+                }
+                check_value(value, true)?;
+            }
+            check_value(&property.value, false)?;
+        }
+        Ok(())
+    }
+
+    /// The typed properties, as `(name, types, value, is_text)`, in the order of RFC6350
+    fn properties(&self) -> Vec<(&'static str, &[String], String, bool)> {
+        let mut properties: Vec<(&'static str, &[String], String, bool)> =
+            vec![("FN", &[], self.formatted_name.clone(), true)];
+        if let Some(name) = &self.name {
+            let components = name.components().map(escape).join(";");
+            properties.push(("N", &[], components, false));
+        }
+        for (property, value) in [
+            ("NICKNAME", &self.nickname),
+            ("ORG", &self.org),
+            ("TITLE", &self.title),
+        ] {
+            if !value.is_empty() {
+                properties.push((property, &[], value.clone(), true));
+            }
+        }
+        for (property, values, is_text) in [
+            ("EMAIL", &self.emails, true),
+            ("TEL", &self.phones, false),
+            ("IMPP", &self.impps, false),
+            ("URL", &self.urls, false),
+        ] {
+            for value in values {
+                properties.push((property, &value.types, value.value.clone(), is_text));
+            }
+        }
+        for (property, value, is_text) in [
+            ("PHOTO", &self.photo, false),
+            ("NOTE", &self.note, true),
+            ("UID", &self.uid, false),
+        ] {
+            if !value.is_empty() {
+                properties.push((property, &[], value.clone(), is_text));
+            }
+        }
+        properties
+    }
+
+    pub fn to_vcard(&self) -> Result<String, MimiContentError> {
+        self.validate()?;
+        let mut out = String::new();
+        push_line(&mut out, "BEGIN:VCARD");
+        push_line(&mut out, "VERSION:4.0");
+        for (property, types, value, is_text) in self.properties() {
+            let mut line = property.to_string();
+            if property == "TEL" && value.contains(':') {
+                line.push_str(";VALUE=uri");
+            }
+            if !types.is_empty() {
+                line.push_str(";TYPE=");
+                line.push_str(&types.join(","));
+            }
+            line.push(':');
+            line.push_str(&if is_text { escape(&value) } else { value });
+            push_line(&mut out, &line);
+        }
+        for property in &self.other_properties {
+            let mut line = property.name.clone();
+            for (name, value) in &property.params {
+                line.push(';');
+                line.push_str(&name.to_ascii_uppercase());
+                line.push('=');
+                let value = caret_encode(value);
+                if value.contains([':', ';', ',']) {
+                    line.push_str(&format!("\"{value}\""));
+                } else {
+                    line.push_str(&value);
+                }
+            }
+            line.push(':');
+            line.push_str(&property.value);
+            push_line(&mut out, &line);
+        }
+        push_line(&mut out, "END:VCARD");
+        Ok(out)
+    }
+
+    pub fn from_vcard(text: &str) -> Result<Self, MimiContentError> {
+        let unfolded = text
+            .replace("\r\n ", "")
+            .replace("\r\n\t", "")
+            .replace("\n ", "")
+            .replace("\n\t", "");
+        let mut lines = unfolded
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty());
+
+        if !lines
+            .next()
+            .is_some_and(|line| line.eq_ignore_ascii_case("BEGIN:VCARD"))
+        {
+            return Err(invalid("missing BEGIN:VCARD"));
+        }
+        let mut card = Self::builder().formatted_name("").build();
+        let mut version = None;
+        let mut ended = false;
+        for line in lines.by_ref() {
+            let line = parse_content_line(line)?;
+            let typed = |value: String| TypedValue {
+                value,
+                types: types_of(&line.params),
+            };
+            match line.name.as_str() {
+                "END" => {
+                    ended = true;
+                    break;
+                }
+                "BEGIN" => return Err(invalid("nested vCards are not supported")),
+                "VERSION" => version = Some(line.value.clone()),
+                "FN" if card.formatted_name.is_empty() => {
+                    card.formatted_name = unescape(&line.value)
+                }
+                "N" => {
+                    card.name = Some(ContactName::from_components(&split_components(&line.value)))
+                }
+                "NICKNAME" => card.nickname = unescape(&line.value),
+                "ORG" => card.org = unescape(&line.value),
+                "TITLE" => card.title = unescape(&line.value),
+                "EMAIL" => card.emails.push(typed(unescape(&line.value))),
+                "TEL" => card.phones.push(typed(line.value.clone())),
+                "IMPP" => card.impps.push(typed(line.value.clone())),
+                "URL" => card.urls.push(typed(line.value.clone())),
+                "PHOTO" => card.photo = line.value.clone(),
+                "NOTE" => card.note = unescape(&line.value),
+                "UID" => card.uid = line.value.clone(),
+                _ => card.other_properties.push(VcardProperty {
+                    name: line.name.clone(),
+                    params: line.params.clone(),
+                    value: line.value.clone(),
+                }),
+            }
+        }
+        if !ended || lines.next().is_some() {
+            return Err(invalid("expected END:VCARD at the end"));
+        }
+        if version.as_deref() != Some("4.0") {
+            return Err(invalid("the version (VERSION) is not 4.0"));
+        }
+        card.validate()?;
+        Ok(card)
+    }
+
+    pub fn to_jcard(&self) -> Result<Value, MimiContentError> {
+        self.validate()?;
+        let string = |text: &str| Value::String(text.to_string());
+        let property = |name: &str, types: &[String], value_type: &str, value: Value| {
+            let params = match types {
+                [] => vec![],
+                [single] => vec![("type".to_string(), string(single))],
+                types => vec![(
+                    "type".to_string(),
+                    Value::Array(types.iter().map(|t| string(t)).collect()),
+                )],
+            };
+            Value::Array(vec![
+                string(&name.to_ascii_lowercase()),
+                Value::Object(params.into_iter().collect()),
+                string(value_type),
+                value,
+            ])
+        };
+
+        let mut properties = vec![property("VERSION", &[], "text", string("4.0"))];
+        for (name, types, value, is_text) in self.properties() {
+            properties.push(if name == "N" {
+                let contact_name = self.name.as_ref().expect("N is only listed with a name");
+                let components = contact_name.components().map(string).to_vec();
+                property(name, types, "text", Value::Array(components))
+            } else {
+                let value_type = if is_text || (name == "TEL" && !value.contains(':')) {
+                    "text"
+                } else {
+                    "uri"
+                };
+                property(name, types, value_type, string(&value))
+            });
+        }
+        for other in &self.other_properties {
+            properties.push(Value::Array(vec![
+                string(&other.name.to_ascii_lowercase()),
+                Value::Object(
+                    other
+                        .params
+                        .iter()
+                        .map(|(name, value)| (name.clone(), string(value)))
+                        .collect(),
+                ),
+                string("unknown"),
+                string(&other.value),
+            ]));
+        }
+        Ok(Value::Array(vec![
+            string("vcard"),
+            Value::Array(properties),
+        ]))
+    }
+
+    pub fn from_jcard(json: &str) -> Result<Self, MimiContentError> {
+        let jcard: Value =
+            serde_json::from_str(json).map_err(|_| invalid("the jCard is not valid JSON"))?;
+        let Value::Array(jcard) = jcard else {
+            return Err(invalid("a jCard is an array"));
+        };
+        let [Value::String(vcard), Value::Array(properties)] = jcard.as_slice() else {
+            return Err(invalid("expected [\"vcard\", [properties]]"));
+        };
+        if vcard != "vcard" {
+            return Err(invalid("expected [\"vcard\", [properties]]"));
+        }
+
+        let mut card = Self::builder().formatted_name("").build();
+        let mut version = None;
+        for property in properties {
+            let Some(
+                [Value::String(name), Value::Object(params), Value::String(_value_type), value, ..],
+            ) = property.as_array().map(Vec::as_slice)
+            else {
+                return Err(invalid("a jCard property is [name, params, type, value]"));
+            };
+            let params: Vec<(String, String)> = params
+                .iter()
+                .filter_map(|(name, value)| {
+                    let value = match value {
+                        Value::String(text) => text.clone(),
+                        Value::Array(values) => values
+                            .iter()
+                            .filter_map(Value::as_str)
+                            .collect::<Vec<_>>()
+                            .join(","),
+                        _ => return None,
+                    };
+                    Some((name.to_ascii_lowercase(), value))
+                })
+                .collect();
+            let text = || value.as_str().map(str::to_string).unwrap_or_default();
+            let typed = || TypedValue {
+                value: text(),
+                types: types_of(&params),
+            };
+            match name.to_ascii_uppercase().as_str() {
+                "VERSION" => version = Some(text()),
+                "FN" if card.formatted_name.is_empty() => card.formatted_name = text(),
+                "N" => {
+                    let components: Vec<String> = match value {
+                        Value::Array(components) => components
+                            .iter()
+                            .map(|component| component.as_str().unwrap_or_default().to_string())
+                            .collect(),
+                        _ => split_components(&text()),
+                    };
+                    card.name = Some(ContactName::from_components(&components));
+                }
+                "NICKNAME" => card.nickname = text(),
+                "ORG" => card.org = text(),
+                "TITLE" => card.title = text(),
+                "EMAIL" => card.emails.push(typed()),
+                "TEL" => card.phones.push(typed()),
+                "IMPP" => card.impps.push(typed()),
+                "URL" => card.urls.push(typed()),
+                "PHOTO" => card.photo = text(),
+                "NOTE" => card.note = text(),
+                "UID" => card.uid = text(),
+                other => {
+                    if let Some(value) = value.as_str() {
+                        card.other_properties.push(VcardProperty {
+                            name: other.to_string(),
+                            params,
+                            value: value.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        if version.as_deref() != Some("4.0") {
+            return Err(invalid("the version (VERSION) is not 4.0"));
+        }
+        card.validate()?;
+        Ok(card)
+    }
+
+    pub fn to_single_part(&self, format: CardFormat) -> Result<SinglePart, MimiContentError> {
+        Ok(match format {
+            CardFormat::VCard => SinglePart::from_text(TEXT_VCARD, self.to_vcard()?),
+            CardFormat::JCard => SinglePart {
+                content_type: APPLICATION_VCARD_JSON.into(),
+                content: self.to_jcard()?.to_string().into_bytes().into(),
+            },
+        })
+    }
+
+    pub fn from_single_part(single: &SinglePart) -> Result<Self, MimiContentError> {
+        let media_type = single.media_type()?;
+        if media_type.same_essence(&TEXT_VCARD) {
+            Self::from_vcard(&single.text()?)
+        } else if media_type.same_essence(&APPLICATION_VCARD_JSON) {
+            let json = std::str::from_utf8(&single.content)
+                .map_err(|_| invalid("the jCard is not UTF-8"))?;
+            Self::from_jcard(json)
+        } else {
+            Err(invalid("not a vCard or a jCard"))
+        }
+    }
+
+    /// The part to send, with the `profile` disposition
+    pub fn to_nested_part(&self, format: CardFormat) -> Result<NestedPart, MimiContentError> {
+        Ok(NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Profile))
+            .part_content(NestedPartContent::SinglePart(self.to_single_part(format)?))
+            .build())
+    }
+
+    /// Reads a contact card part (its disposition isn't checked)
+    pub fn from_nested_part(part: &NestedPart) -> Result<Self, MimiContentError> {
+        match &part.part_content {
+            NestedPartContent::SinglePart(single) => Self::from_single_part(single),
+            _ => Err(invalid("the contact card is not a single part")),
+        }
+    }
+}
+
+impl MimiContent {
+    /// The valid contact cards among the parts with the `profile` disposition
+    pub fn contact_cards(&self) -> Vec<ContactCard> {
+        let mut cards = vec![];
+        self.accept(&mut |part: &NestedPart, info: &PartInfo<'_>| {
+            if info.disposition != Disposition::Base(BaseDispos::Profile) {
+                return Walk::Continue;
+            }
+            cards.extend(ContactCard::from_nested_part(part).ok());
+            Walk::SkipChildren
+        });
+        cards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alice() -> ContactCard {
+        ContactCard::builder()
+            .formatted_name("Alice Smith")
+            .name(ContactName {
+                family: "Smith".into(),
+                given: "Alice".into(),
+                ..Default::default()
+            })
+            .org("Example, Inc.")
+            .emails(vec![TypedValue {
+                value: "alice@example.com".into(),
+                types: vec!["work".into()],
+            }])
+            .phones(vec![TypedValue {
+                value: "tel:+1-555-555-0100".into(),
+                types: vec!["cell".into(), "voice".into()],
+            }])
+            .impps(vec!["mimi://example.com/u/alice-smith".into()])
+            .note("Line one\nLine two; with a long enough text to be folded over several lines of the card")
+            .other_properties(vec![VcardProperty {
+                name: "X-PRONOUNS".into(),
+                params: vec![],
+                value: "she/her".into(),
+            }])
+            .build()
+    }
+
+    #[test]
+    fn roundtrips_vcards() {
+        let vcard = alice().to_vcard().unwrap();
+        assert!(vcard
+            .starts_with("BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Alice Smith\r\nN:Smith;Alice;;;\r\n"));
+        assert!(vcard.contains("ORG:Example\\, Inc.\r\n"));
+        assert!(vcard.contains("TEL;VALUE=uri;TYPE=cell,voice:tel:+1-555-555-0100\r\n"));
+        assert!(vcard.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert_eq!(ContactCard::from_vcard(&vcard).unwrap(), alice());
+
+        let minimal = "BEGIN:VCARD\nVERSION:4.0\nitem1.FN:Bob\nEMAIL;TYPE=\"home\":bob@example.com\nEND:VCARD\n";
+        let bob = ContactCard::from_vcard(minimal).unwrap();
+        assert_eq!(bob.formatted_name, "Bob");
+        assert_eq!(bob.emails[0].types, vec!["home"]);
+    }
+
+    #[test]
+    fn validates_required_properties() {
+        for (vcard, reason) in [
+            (
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nEND:VCARD\r\n",
+                "missing formatted name (FN)",
+            ),
+            (
+                "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Bob\r\nEND:VCARD\r\n",
+                "the version (VERSION) is not 4.0",
+            ),
+            (
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Bob\r\n",
+                "expected END:VCARD at the end",
+            ),
+            (
+                "VERSION:4.0\r\nFN:Bob\r\nEND:VCARD\r\n",
+                "missing BEGIN:VCARD",
+            ),
+        ] {
+            assert!(
+                matches!(ContactCard::from_vcard(vcard), Err(MimiContentError::InvalidContactCard(r)) if r == reason),
+                "{vcard:?}"
+            );
+        }
+        assert!(ContactCard::builder()
+            .formatted_name(" ")
+            .build()
+            .to_vcard()
+            .is_err());
+    }
+
+    #[test]
+    fn roundtrips_jcards_in_profile_parts() {
+        let jcard = alice().to_jcard().unwrap().to_string();
+        assert!(jcard.starts_with(r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text","Alice Smith"],["n",{},"text",["Smith","Alice","","",""]]"#));
+        assert_eq!(ContactCard::from_jcard(&jcard).unwrap(), alice());
+
+        let mut mimi_content = MimiContent::builder()
+            .salt_from_outside_entropy(Default::default())
+            .topic_id(Default::default())
+            .nested_part(alice().to_nested_part(CardFormat::JCard).unwrap())
+            .build();
+        assert_eq!(mimi_content.contact_cards(), vec![alice()]);
+        mimi_content.nested_part = alice().to_nested_part(CardFormat::VCard).unwrap();
+        assert_eq!(mimi_content.contact_cards(), vec![alice()]);
+        assert_eq!(
+            mimi_content.nested_part.disposition,
+            Disposition::Base(BaseDispos::Profile)
+        );
+    }
+
+    #[test]
+    fn rejects_vcard_injection() {
+        let injected = |card: ContactCard| {
+            matches!(
+                card.to_vcard(),
+                Err(MimiContentError::InvalidContactCard(_))
+            )
+        };
+        assert!(injected(
+            ContactCard::builder()
+                .formatted_name("Mallory")
+                .uid("1\r\nFN:Alice")
+                .build()
+        ));
+        assert!(injected(
+            ContactCard::builder()
+                .formatted_name("Mallory\rFN:Alice")
+                .build()
+        ));
+        assert!(injected(
+            ContactCard::builder()
+                .formatted_name("Mallory")
+                .emails(vec![TypedValue {
+                    value: "mallory@example.com".into(),
+                    types: vec!["work:x".into()],
+                }])
+                .build()
+        ));
+        for property in [
+            VcardProperty {
+                name: "X-A:B".into(),
+                params: vec![],
+                value: "c".into(),
+            },
+            VcardProperty {
+                name: "END".into(),
+                params: vec![],
+                value: "VCARD".into(),
+            },
+            VcardProperty {
+                name: "X-A".into(),
+                params: vec![("x-b=c;d".into(), "e".into())],
+                value: "f".into(),
+            },
+            VcardProperty {
+                name: "X-A".into(),
+                params: vec![],
+                value: "b\nEND:VCARD".into(),
+            },
+        ] {
+            let card = ContactCard::builder()
+                .formatted_name("Mallory")
+                .other_properties(vec![property.clone()])
+                .build();
+            assert!(injected(card), "{property:?}");
+        }
+
+        let quoted = ContactCard::builder()
+            .formatted_name("Mallory")
+            .other_properties(vec![VcardProperty {
+                name: "X-A".into(),
+                params: vec![("x-b".into(), "\":FN:Alice;^\nx".into())],
+                value: "c".into(),
+            }])
+            .build();
+        let vcard = quoted.to_vcard().unwrap();
+        assert!(
+            vcard.contains("X-A;X-B=\"^':FN:Alice;^^^nx\":c\r\n"),
+            "{vcard}"
+        );
+        assert_eq!(ContactCard::from_vcard(&vcard).unwrap(), quoted);
+    }
+
+    #[test]
+    fn parses_jcards_as_strict_json() {
+        let jcard = r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text","Bob"]]]"#;
+        assert_eq!(
+            ContactCard::from_jcard(jcard).unwrap().formatted_name,
+            "Bob"
+        );
+        for jcard in [
+            r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text",h'aeb0']]]"#,
+            r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text","Bob"],]]"#,
+            r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text",'Bob']]]"#,
+            r#"["vcard",[["version",{},"text","4.0"],["fn",{},"text","Bob"]]] / comment /"#,
+        ] {
+            assert!(
+                matches!(
+                    ContactCard::from_jcard(jcard),
+                    Err(MimiContentError::InvalidContactCard(
+                        "the jCard is not valid JSON"
+                    ))
+                ),
+                "{jcard}"
+            );
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Reactions, whose content is a single emoji
+//!
+//! Senders and clients disagree on variation selectors (`❤` versus `❤️`), so reactions are
+//! compared in their fully-qualified form, optionally ignoring skin tones.
+
+use emojis::Emoji;
+use indexmap::IndexMap;
+
+use crate::{
+    BaseDispos, Disposition, MediaType, MimiContent, MimiContentError, NestedPartContent,
+    SinglePart,
+};
+
+/// Whether reactions that only differ by skin tone are counted together
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SkinTones {
+    #[default]
+    Keep,
+    Merge,
+}
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidReaction(reason)
+}
+
+/// The emoji of a reaction, which must be exactly one RGI emoji (fully-qualified or not, and
+/// possibly followed by a text presentation selector)
+pub fn reaction_emoji(text: &str) -> Result<&'static Emoji, MimiContentError> {
+    if text.is_empty() {
+        return Err(invalid("no emoji"));
+    }
+    emojis::get(text)
+        .or_else(|| emojis::get(&text.replace('\u{FE0E}', "")))
+        .ok_or(invalid("not a single emoji"))
+}
+
+/// The form of the emoji under which equivalent reactions are counted
+pub fn normalize_reaction(
+    text: &str,
+    skin_tones: SkinTones,
+) -> Result<&'static str, MimiContentError> {
+    let emoji = reaction_emoji(text)?;
+    let emoji = match skin_tones {
+        SkinTones::Keep => emoji,
+        SkinTones::Merge => emoji
+            .with_skin_tone(emojis::SkinTone::Default)
+            .unwrap_or(emoji),
+    };
+    Ok(emoji.as_str())
+}
+
+/// Replaces the known `:shortcode:`s (as on GitHub) with their emoji, leaving the rest as is
+pub fn expand_shortcodes(text: &str) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(':') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let emoji = after.find(':').and_then(|end| {
+            let shortcode = &after[..end];
+            let valid = shortcode
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'+' | b'-'));
+            let emoji = emojis::get_by_shortcode(shortcode).filter(|_| valid)?;
+            Some((emoji, end))
+        });
+        match emoji {
+            Some((emoji, end)) => {
+                expanded.push_str(emoji.as_str());
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push(':');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+impl SinglePart {
+    /// The emoji of a `text/plain` reaction part
+    pub fn reaction_emoji(&self) -> Result<&'static Emoji, MimiContentError> {
+        if !self.media_type()?.same_essence(&MediaType::TEXT_PLAIN) {
+            return Err(invalid("not text/plain"));
+        }
+        reaction_emoji(&self.text()?)
+    }
+}
+
+impl MimiContent {
+    /// The emoji of a reaction message, whose nested part is a single part with the
+    /// `reaction` disposition
+    pub fn reaction_emoji(&self) -> Result<&'static Emoji, MimiContentError> {
+        if self.nested_part.disposition != Disposition::Base(BaseDispos::Reaction) {
+            return Err(invalid("not a reaction"));
+        }
+        let NestedPartContent::SinglePart(single) = &self.nested_part.part_content else {
+            return Err(invalid("not a single part"));
+        };
+        single.reaction_emoji()
+    }
+}
+
+/// Counts the valid reactions by normalized emoji, in order of first appearance. Removed
+/// reactions must already be left out.
+pub fn count_reactions<'a>(
+    reactions: impl IntoIterator<Item = &'a MimiContent>,
+    skin_tones: SkinTones,
+) -> IndexMap<&'static str, usize> {
+    let mut counts = IndexMap::new();
+    for reaction in reactions {
+        let Ok(emoji) = reaction.reaction_emoji() else {
+            continue;
+        };
+        if let Ok(emoji) = normalize_reaction(emoji.as_str(), skin_tones) {
+            *counts.entry(emoji).or_default() += 1;
+        }
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MimiContentDeserialize as _, NestedPart};
+
+    #[test]
+    fn validates_and_normalizes_reactions() {
+        assert_eq!(reaction_emoji("👍").unwrap().as_str(), "👍");
+        // U+2764 without its emoji presentation selector
+        assert_eq!(normalize_reaction("❤", SkinTones::Keep).unwrap(), "❤️");
+        assert_eq!(
+            normalize_reaction("❤\u{FE0E}", SkinTones::Keep).unwrap(),
+            "❤️"
+        );
+        assert_eq!(normalize_reaction("👍🏽", SkinTones::Keep).unwrap(), "👍🏽");
+        assert_eq!(normalize_reaction("👍🏽", SkinTones::Merge).unwrap(), "👍");
+        assert_eq!(normalize_reaction("👩🏿‍❤️‍👨🏼", SkinTones::Merge).unwrap(), "👩‍❤️‍👨");
+
+        for text in ["", "+1", "👍👍", "👍 ", "a"] {
+            assert!(reaction_emoji(text).is_err(), "{text:?}");
+        }
+    }
+
+    #[test]
+    fn expands_shortcodes() {
+        assert_eq!(expand_shortcodes(":+1:"), "👍");
+        assert_eq!(
+            expand_shortcodes("ship it :rocket::tada: at 10:30:00 :no_such_emoji:"),
+            "ship it 🚀🎉 at 10:30:00 :no_such_emoji:"
+        );
+        assert_eq!(expand_shortcodes("a:heart:"), "a❤️");
+    }
+
+    #[test]
+    fn counts_equivalent_reactions_together() {
+        let example =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/reaction.cbor"))
+                .unwrap();
+        assert_eq!(example.reaction_emoji().unwrap().as_str(), "❤️");
+
+        let reaction = |text: &str| MimiContent {
+            nested_part: NestedPart::builder()
+                .disposition(Disposition::Base(BaseDispos::Reaction))
+                .part_content(NestedPartContent::SinglePart(SinglePart::from_text(
+                    MediaType::TEXT_PLAIN_UTF8,
+                    text.to_string(),
+                )))
+                .build(),
+            ..example.clone()
+        };
+        let reactions = [
+            example.clone(),
+            reaction("❤️"),
+            reaction("👍🏻"),
+            reaction("👍"),
+            reaction("not an emoji"),
+        ];
+        assert_eq!(
+            count_reactions(&reactions, SkinTones::Keep),
+            IndexMap::from([("❤️", 2), ("👍🏻", 1), ("👍", 1)])
+        );
+        assert_eq!(
+            count_reactions(&reactions, SkinTones::Merge),
+            IndexMap::from([("❤️", 2), ("👍", 2)])
+        );
+    }
+}
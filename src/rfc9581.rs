@@ -1,12 +1,33 @@
-use serde::ser::{SerializeMap as _, SerializeSeq};
+//! The subset of [RFC9581](https://www.rfc-editor.org/rfc/rfc9581.html) extended times,
+//! durations and periods that messages need: whole seconds with an optional nanosecond
+//! fraction
+
+use serde::ser::{SerializeMap as _, SerializeSeq as _};
 
 pub type ExtendedTime = ciborium::tag::Required<ExtendedTimeDetailed, 1001>;
 pub type Duration = ciborium::tag::Required<ExtendedTimeDetailed, 1002>;
 pub type Period = ciborium::tag::Required<PeriodInner, 1003>;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Map key of the base time, in seconds
+const BASE_TIME: i64 = 1;
+/// Map keys of the fractions of a second
+const MILLISECONDS: i64 = -3;
+const MICROSECONDS: i64 = -6;
+const NANOSECONDS: i64 = -9;
+
+/// A time relative to the epoch (in an [`ExtendedTime`]) or a length of time (in a
+/// [`Duration`])
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ExtendedTimeDetailed {
-    pub base_time: std::time::SystemTime, // TODO: switch to a WASM-friendly time
+    pub secs: i64,
+    /// Always below `1_000_000_000`
+    pub nanos: u32,
+}
+
+impl ExtendedTimeDetailed {
+    pub const fn from_secs(secs: i64) -> Self {
+        Self { secs, nanos: 0 }
+    }
 }
 
 impl<'de> serde::Deserialize<'de> for ExtendedTimeDetailed {
@@ -26,9 +47,37 @@ impl<'de> serde::Deserialize<'de> for ExtendedTimeDetailed {
             where
                 A: serde::de::MapAccess<'de>,
             {
-                while let Some((k, v)) = map.next_entry()? {}
-
-                todo!()
+                use serde::de::Error as _;
+                let mut secs = None;
+                let mut nanos = 0u64;
+                while let Some(key) = map.next_key::<i64>()? {
+                    let scale = match key {
+                        BASE_TIME => {
+                            secs = Some(map.next_value::<i64>()?);
+                            continue;
+                        }
+                        MILLISECONDS => 1_000_000,
+                        MICROSECONDS => 1_000,
+                        NANOSECONDS => 1,
+                        // negative keys are critical, and must be understood
+                        key if key < 0 => {
+                            return Err(A::Error::custom(format!("unsupported critical key {key}")))
+                        }
+                        _ => {
+                            map.next_value::<serde::de::IgnoredAny>()?;
+                            continue;
+                        }
+                    };
+                    let fraction = map.next_value::<u64>()?;
+                    if fraction >= 1_000_000_000 / scale {
+                        return Err(A::Error::custom("the fraction exceeds a second"));
+                    }
+                    nanos = fraction * scale;
+                }
+                Ok(ExtendedTimeDetailed {
+                    secs: secs.ok_or_else(|| A::Error::missing_field("base time"))?,
+                    nanos: nanos as u32,
+                })
             }
         }
 
@@ -41,14 +90,17 @@ impl serde::Serialize for ExtendedTimeDetailed {
     where
         S: serde::Serializer,
     {
-        let len = 1;
+        let len = if self.nanos == 0 { 1 } else { 2 };
         let mut map = serializer.serialize_map(Some(len))?;
-
-        // TODO: Finish this
+        map.serialize_entry(&BASE_TIME, &self.secs)?;
+        if self.nanos != 0 {
+            map.serialize_entry(&NANOSECONDS, &self.nanos)?;
+        }
         map.end()
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PeriodInner {
     Span {
         start: ExtendedTime,
@@ -72,6 +124,79 @@ impl PeriodInner {
             PeriodInner::DurationEndsAt { .. } => 3,
         }
     }
+
+    pub fn span(start: ExtendedTimeDetailed, end: ExtendedTimeDetailed) -> Period {
+        Period::from(PeriodInner::Span {
+            start: ExtendedTime::from(start),
+            end: ExtendedTime::from(end),
+        })
+    }
+
+    /// The start of the period, or `None` if it is out of range
+    pub fn start(&self) -> Option<ExtendedTimeDetailed> {
+        match self {
+            PeriodInner::Span { start, .. } | PeriodInner::DurationStartsAt { start, .. } => {
+                Some(start.0)
+            }
+            PeriodInner::DurationEndsAt { duration, end } => sub(end.0, duration.0),
+        }
+    }
+
+    /// The end of the period, or `None` if it is out of range
+    pub fn end(&self) -> Option<ExtendedTimeDetailed> {
+        match self {
+            PeriodInner::Span { end, .. } | PeriodInner::DurationEndsAt { end, .. } => Some(end.0),
+            PeriodInner::DurationStartsAt { duration, start } => add(start.0, duration.0),
+        }
+    }
+}
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+fn add(time: ExtendedTimeDetailed, duration: ExtendedTimeDetailed) -> Option<ExtendedTimeDetailed> {
+    if time.nanos >= NANOS_PER_SEC || duration.nanos >= NANOS_PER_SEC {
+        return None;
+    }
+    let nanos = time.nanos + duration.nanos;
+    Some(ExtendedTimeDetailed {
+        secs: time
+            .secs
+            .checked_add(duration.secs)?
+            .checked_add(i64::from(nanos / NANOS_PER_SEC))?,
+        nanos: nanos % NANOS_PER_SEC,
+    })
+}
+
+fn sub(time: ExtendedTimeDetailed, duration: ExtendedTimeDetailed) -> Option<ExtendedTimeDetailed> {
+    if time.nanos >= NANOS_PER_SEC || duration.nanos >= NANOS_PER_SEC {
+        return None;
+    }
+    let (nanos, borrow) = match time.nanos.checked_sub(duration.nanos) {
+        Some(nanos) => (nanos, 0),
+        None => (time.nanos + NANOS_PER_SEC - duration.nanos, 1),
+    };
+    Some(ExtendedTimeDetailed {
+        secs: time.secs.checked_sub(duration.secs)?.checked_sub(borrow)?,
+        nanos,
+    })
+}
+
+impl From<ExtendedTimeDetailed> for ExtendedTime {
+    fn from(time: ExtendedTimeDetailed) -> Self {
+        Self(time)
+    }
+}
+
+impl From<ExtendedTimeDetailed> for Duration {
+    fn from(duration: ExtendedTimeDetailed) -> Self {
+        Self(duration)
+    }
+}
+
+impl From<PeriodInner> for Period {
+    fn from(period: PeriodInner) -> Self {
+        Self(period)
+    }
 }
 
 impl serde::Serialize for PeriodInner {
@@ -154,3 +279,77 @@ impl<'de> serde::Deserialize<'de> for PeriodInner {
         deserializer.deserialize_seq(PeriodInnerVisitor)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn periods_roundtrip() {
+        let start = ExtendedTimeDetailed::from_secs(1_700_000_000);
+        let period = Period::from(PeriodInner::DurationStartsAt {
+            duration: Duration::from(ExtendedTimeDetailed {
+                secs: 3600,
+                nanos: 500_000_000,
+            }),
+            start: ExtendedTime::from(start),
+        });
+
+        let mut bytes = vec![];
+        ciborium::into_writer(&period, &mut bytes).unwrap();
+        // 1003([1001({1: 1700000000}), null, 1002({1: 3600, -9: 500000000})])
+        assert_eq!(&bytes[..4], b"\xD9\x03\xEB\x83");
+        let decoded: Period = ciborium::from_reader(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, period);
+        assert_eq!(decoded.0.start(), Some(start));
+        assert_eq!(
+            decoded.0.end(),
+            Some(ExtendedTimeDetailed {
+                secs: 1_700_003_600,
+                nanos: 500_000_000
+            })
+        );
+
+        let milliseconds: ExtendedTimeDetailed =
+            ciborium::from_reader(&b"\xA2\x01\x1A\x65\x53\xF1\x00\x22\x19\x01\xF4"[..]).unwrap();
+        assert_eq!(milliseconds.nanos, 500_000_000);
+        // an unknown critical key
+        assert!(
+            ciborium::from_reader::<ExtendedTimeDetailed, _>(&b"\xA2\x01\x00\x20\x00"[..]).is_err()
+        );
+    }
+
+    #[test]
+    fn out_of_range_periods_have_no_bounds() {
+        let late = Period::from(PeriodInner::DurationStartsAt {
+            duration: Duration::from(ExtendedTimeDetailed {
+                secs: 0,
+                nanos: 500_000_000,
+            }),
+            start: ExtendedTime::from(ExtendedTimeDetailed {
+                secs: i64::MAX,
+                nanos: 600_000_000,
+            }),
+        });
+        assert_eq!(late.0.end(), None);
+
+        let early = Period::from(PeriodInner::DurationEndsAt {
+            duration: Duration::from(ExtendedTimeDetailed::from_secs(1)),
+            end: ExtendedTime::from(ExtendedTimeDetailed::from_secs(i64::MIN)),
+        });
+        assert_eq!(early.0.start(), None);
+        assert_eq!(
+            early.0.end(),
+            Some(ExtendedTimeDetailed::from_secs(i64::MIN))
+        );
+
+        let invalid = PeriodInner::DurationEndsAt {
+            duration: Duration::from(ExtendedTimeDetailed {
+                secs: 0,
+                nanos: u32::MAX,
+            }),
+            end: ExtendedTime::from(ExtendedTimeDetailed::from_secs(0)),
+        };
+        assert_eq!(invalid.start(), None);
+    }
+}
@@ -0,0 +1,284 @@
+//! Invitations to join a call or conference, sent as parts with the `session` disposition
+//!
+//! The join URIs are sent as a `text/uri-list`, whose first comment line is the title. When
+//! the session is scheduled, its [`Period`] follows in a `ProcessAll` multipart, as an
+//! `application/cbor` part holding the tagged RFC9581 period. The external part form of the
+//! conferencing example of the draft (the join URI as `url`, the title as `description`) is
+//! read too.
+//!
+//! Received join URIs are checked against a [`UrlPolicy`], and those it rejects are dropped.
+
+pub use crate::rfc9581::{Duration, ExtendedTime, ExtendedTimeDetailed, Period, PeriodInner};
+use crate::{
+    safety::UrlPolicy, BaseDispos, Disposition, MediaType, MimiContent, MimiContentError,
+    MultiPart, NestedPart, NestedPartContent, PartInfo, PartSemantics, SinglePart, Walk,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, bon::Builder)]
+pub struct SessionInvite {
+    pub join_uris: Vec<String>,
+    #[builder(into, default)]
+    pub title: String,
+    pub period: Option<Period>,
+}
+
+fn invalid(reason: &'static str) -> MimiContentError {
+    MimiContentError::InvalidSessionInvite(reason)
+}
+
+impl SessionInvite {
+    /// The `text/uri-list` ([RFC2483](https://www.rfc-editor.org/rfc/rfc2483.html#section-5))
+    /// of the join URIs, preceded by the title as a comment
+    pub fn to_uri_list(&self) -> String {
+        let title = self.title.lines().map(|line| format!("# {line}\r\n"));
+        let uris = self.join_uris.iter().map(|uri| format!("{uri}\r\n"));
+        title.chain(uris).collect()
+    }
+
+    /// Reads a `text/uri-list`, taking its first comment line as the title
+    pub fn from_uri_list(uri_list: &str) -> Result<Self, MimiContentError> {
+        let mut title = None;
+        let mut join_uris = vec![];
+        for line in uri_list.lines().map(str::trim) {
+            if let Some(comment) = line.strip_prefix('#') {
+                title.get_or_insert_with(|| comment.trim().to_string());
+            } else if !line.is_empty() {
+                join_uris.push(line.to_string());
+            }
+        }
+        if join_uris.is_empty() {
+            return Err(invalid("no join URI"));
+        }
+        Ok(Self {
+            join_uris,
+            title: title.unwrap_or_default(),
+            period: None,
+        })
+    }
+
+    pub fn to_nested_part(&self) -> Result<NestedPart, MimiContentError> {
+        let uri_list = NestedPartContent::SinglePart(SinglePart::from_text(
+            MediaType::TEXT_URI_LIST,
+            self.to_uri_list(),
+        ));
+        let part_content = match &self.period {
+            None => uri_list,
+            Some(period) => {
+                let mut content = vec![];
+                ciborium::into_writer(period, &mut content)?;
+                let period = SinglePart {
+                    content_type: MediaType::APPLICATION_CBOR.into(),
+                    content: content.into(),
+                };
+                NestedPartContent::MultiPart(MultiPart {
+                    part_semantics: PartSemantics::ProcessAll,
+                    parts: vec![
+                        NestedPart::builder().part_content(uri_list).build(),
+                        NestedPart::builder()
+                            .part_content(NestedPartContent::SinglePart(period))
+                            .build(),
+                    ],
+                })
+            }
+        };
+        Ok(NestedPart::builder()
+            .disposition(Disposition::Base(BaseDispos::Session))
+            .part_content(part_content)
+            .build())
+    }
+
+    /// Reads a session part, as produced by [`Self::to_nested_part`] or as an external part
+    /// (its disposition isn't checked), keeping the join URIs that `policy` allows
+    pub fn from_nested_part(
+        part: &NestedPart,
+        policy: &UrlPolicy,
+    ) -> Result<Self, MimiContentError> {
+        let mut invite = Self::from_nested_part_unchecked(part)?;
+        let mut violation = None;
+        invite.join_uris.retain(|uri| match policy.check(uri) {
+            Ok(_) => true,
+            Err(error) => {
+                violation.get_or_insert(error);
+                false
+            }
+        });
+        match violation {
+            Some(violation) if invite.join_uris.is_empty() => Err(violation.into()),
+            _ => Ok(invite),
+        }
+    }
+
+    fn from_nested_part_unchecked(part: &NestedPart) -> Result<Self, MimiContentError> {
+        match &part.part_content {
+            NestedPartContent::ExternalPart(external) => {
+                if external.url.is_empty() {
+                    return Err(invalid("no join URI"));
+                }
+                Ok(Self {
+                    join_uris: vec![(*external.url).to_string()],
+                    title: (*external.description).to_string(),
+                    period: None,
+                })
+            }
+            NestedPartContent::SinglePart(single) => Self::from_single_part(single),
+            NestedPartContent::MultiPart(multi) => {
+                let [uri_list, period] = multi.parts.as_slice() else {
+                    return Err(invalid("expected a URI list and a period"));
+                };
+                let NestedPartContent::SinglePart(uri_list) = &uri_list.part_content else {
+                    return Err(invalid("the URI list is not a single part"));
+                };
+                let NestedPartContent::SinglePart(period) = &period.part_content else {
+                    return Err(invalid("the period is not a single part"));
+                };
+                if !period
+                    .media_type()?
+                    .same_essence(&MediaType::APPLICATION_CBOR)
+                {
+                    return Err(invalid("the period is not CBOR"));
+                }
+                Ok(Self {
+                    period: Some(ciborium::from_reader(&period.content[..])?),
+                    ..Self::from_single_part(uri_list)?
+                })
+            }
+            _ => Err(invalid("not a single, multi or external part")),
+        }
+    }
+
+    fn from_single_part(single: &SinglePart) -> Result<Self, MimiContentError> {
+        if !single.media_type()?.same_essence(&MediaType::TEXT_URI_LIST) {
+            return Err(invalid("the join URIs are not a URI list"));
+        }
+        Self::from_uri_list(&single.text()?)
+    }
+}
+
+impl MimiContent {
+    /// The valid invites among the parts with the `session` disposition, with the join URIs
+    /// that the default [`UrlPolicy`] allows
+    pub fn session_invites(&self) -> Vec<SessionInvite> {
+        self.session_invites_with(&UrlPolicy::default())
+    }
+
+    /// Same as [`Self::session_invites`], with the join URIs that `policy` allows
+    pub fn session_invites_with(&self, policy: &UrlPolicy) -> Vec<SessionInvite> {
+        let mut invites = vec![];
+        self.accept(&mut |part: &NestedPart, info: &PartInfo<'_>| {
+            if info.disposition != Disposition::Base(BaseDispos::Session) {
+                return Walk::Continue;
+            }
+            invites.extend(SessionInvite::from_nested_part(part, policy).ok());
+            Walk::SkipChildren
+        });
+        invites
+    }
+
+    /// Whether the message invites to a session, and should be presented as such
+    pub fn is_session_invite(&self) -> bool {
+        !self.session_invites().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::safety::UrlViolation;
+    use crate::MimiContentDeserialize as _;
+
+    #[test]
+    fn reads_the_conferencing_example() {
+        let mimi_content =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/conferencing.cbor"))
+                .unwrap();
+        assert!(mimi_content.is_session_invite());
+        assert_eq!(
+            mimi_content.session_invites(),
+            vec![SessionInvite::builder()
+                .join_uris(vec!["https://example.com/join/12345".into()])
+                .title("Join the Foo 118 conference")
+                .build()]
+        );
+
+        let original =
+            MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/original.cbor"))
+                .unwrap();
+        assert!(!original.is_session_invite());
+    }
+
+    #[test]
+    fn roundtrips_uri_lists_and_periods() {
+        let invite = SessionInvite::builder()
+            .join_uris(vec![
+                "https://example.com/join/12345".into(),
+                "https://backup.example.com/join/12345".into(),
+            ])
+            .title("Weekly sync")
+            .build();
+        assert_eq!(
+            invite.to_uri_list(),
+            "# Weekly sync\r\nhttps://example.com/join/12345\r\nhttps://backup.example.com/join/12345\r\n"
+        );
+        let part = invite.to_nested_part().unwrap();
+        assert_eq!(part.disposition, Disposition::Base(BaseDispos::Session));
+        assert_eq!(
+            SessionInvite::from_nested_part(&part, &UrlPolicy::default()).unwrap(),
+            invite
+        );
+
+        let scheduled = SessionInvite {
+            period: Some(PeriodInner::span(
+                ExtendedTimeDetailed::from_secs(1_700_000_000),
+                ExtendedTimeDetailed::from_secs(1_700_001_800),
+            )),
+            ..invite
+        };
+        let part = scheduled.to_nested_part().unwrap();
+        assert_eq!(
+            SessionInvite::from_nested_part(&part, &UrlPolicy::default()).unwrap(),
+            scheduled
+        );
+
+        assert!(SessionInvite::from_uri_list("# only a comment\r\n").is_err());
+    }
+
+    #[test]
+    fn drops_unsafe_join_uris() {
+        let invite = |join_uris: &[&str]| {
+            SessionInvite::builder()
+                .join_uris(join_uris.iter().map(|uri| uri.to_string()).collect())
+                .build()
+                .to_nested_part()
+                .unwrap()
+        };
+        let policy = UrlPolicy::default();
+
+        let part = invite(&[
+            "javascript:alert(1)",
+            "https://example.com/join/12345",
+            "http://127.0.0.1/join",
+        ]);
+        assert_eq!(
+            SessionInvite::from_nested_part(&part, &policy)
+                .unwrap()
+                .join_uris,
+            vec!["https://example.com/join/12345".to_string()]
+        );
+
+        let part = invite(&["https://192.168.0.1/join"]);
+        assert!(matches!(
+            SessionInvite::from_nested_part(&part, &policy),
+            Err(MimiContentError::UnsafeUrl(UrlViolation::NonPublicHost(_)))
+        ));
+        let mimi_content = MimiContent {
+            nested_part: part,
+            ..Default::default()
+        };
+        assert!(!mimi_content.is_session_invite());
+
+        let policy = UrlPolicy::builder()
+            .allowed_hosts(vec!["192.168.0.1".to_string()])
+            .build();
+        assert_eq!(mimi_content.session_invites_with(&policy).len(), 1);
+    }
+}
@@ -0,0 +1,334 @@
+//! Detection of the media type of content from its magic numbers, to catch parts whose
+//! declared content type doesn't match their bytes (e.g. an executable labeled `image/png`)
+
+use crate::{media_type::media_type, ExternalPart, MediaType, MimiContentError, SinglePart};
+
+const IMAGE_PNG: MediaType = media_type("image", "png", &[]);
+const IMAGE_JPEG: MediaType = media_type("image", "jpeg", &[]);
+const IMAGE_GIF: MediaType = media_type("image", "gif", &[]);
+const IMAGE_WEBP: MediaType = media_type("image", "webp", &[]);
+const IMAGE_BMP: MediaType = media_type("image", "bmp", &[]);
+const IMAGE_TIFF: MediaType = media_type("image", "tiff", &[]);
+const IMAGE_HEIC: MediaType = media_type("image", "heic", &[]);
+const IMAGE_AVIF: MediaType = media_type("image", "avif", &[]);
+const AUDIO_MPEG: MediaType = media_type("audio", "mpeg", &[]);
+const AUDIO_WAV: MediaType = media_type("audio", "wav", &[]);
+const AUDIO_FLAC: MediaType = media_type("audio", "flac", &[]);
+const AUDIO_MP4: MediaType = media_type("audio", "mp4", &[]);
+const AUDIO_OGG: MediaType = media_type("audio", "ogg", &[]);
+const VIDEO_MP4: MediaType = media_type("video", "mp4", &[]);
+const VIDEO_QUICKTIME: MediaType = media_type("video", "quicktime", &[]);
+const VIDEO_WEBM: MediaType = media_type("video", "webm", &[]);
+const VIDEO_AVI: MediaType = media_type("video", "x-msvideo", &[]);
+const APPLICATION_PDF: MediaType = media_type("application", "pdf", &[]);
+const APPLICATION_ZIP: MediaType = media_type("application", "zip", &[]);
+const APPLICATION_GZIP: MediaType = media_type("application", "gzip", &[]);
+const APPLICATION_WASM: MediaType = media_type("application", "wasm", &[]);
+const APPLICATION_ELF: MediaType = media_type("application", "x-elf", &[]);
+const APPLICATION_MSDOWNLOAD: MediaType = media_type("application", "x-msdownload", &[]);
+const APPLICATION_MACH_O: MediaType = media_type("application", "x-mach-binary", &[]);
+const TEXT_X_SHELLSCRIPT: MediaType = media_type("text", "x-shellscript", &[]);
+
+/// Byte signatures at the start of the content
+const SIGNATURES: &[(&[u8], MediaType)] = &[
+    (b"\x89PNG\r\n\x1a\n", IMAGE_PNG),
+    (b"\xFF\xD8\xFF", IMAGE_JPEG),
+    (b"GIF87a", IMAGE_GIF),
+    (b"GIF89a", IMAGE_GIF),
+    (b"II*\0", IMAGE_TIFF),
+    (b"MM\0*", IMAGE_TIFF),
+    (b"ID3", AUDIO_MPEG),
+    (b"fLaC", AUDIO_FLAC),
+    (b"OggS", AUDIO_OGG),
+    (b"\x1A\x45\xDF\xA3", VIDEO_WEBM),
+    (b"%PDF-", APPLICATION_PDF),
+    (b"PK\x03\x04", APPLICATION_ZIP),
+    (b"PK\x05\x06", APPLICATION_ZIP),
+    (b"\x1F\x8B", APPLICATION_GZIP),
+    (b"\0asm", APPLICATION_WASM),
+    (b"\x7FELF", APPLICATION_ELF),
+    (b"MZ", APPLICATION_MSDOWNLOAD),
+    (b"\xCF\xFA\xED\xFE", APPLICATION_MACH_O),
+    (b"\xCE\xFA\xED\xFE", APPLICATION_MACH_O),
+    (b"\xCA\xFE\xBA\xBE", APPLICATION_MACH_O),
+    (b"#!", TEXT_X_SHELLSCRIPT),
+];
+
+/// Types of [RIFF](https://en.wikipedia.org/wiki/Resource_Interchange_File_Format) containers
+const RIFF_TYPES: &[(&[u8; 4], MediaType)] = &[
+    (b"WEBP", IMAGE_WEBP),
+    (b"WAVE", AUDIO_WAV),
+    (b"AVI ", VIDEO_AVI),
+];
+
+/// Major brands of ISO base media files (MP4, QuickTime, HEIF)
+const FTYP_BRANDS: &[(&[u8; 4], MediaType)] = &[
+    (b"qt  ", VIDEO_QUICKTIME),
+    (b"heic", IMAGE_HEIC),
+    (b"heix", IMAGE_HEIC),
+    (b"mif1", IMAGE_HEIC),
+    (b"avif", IMAGE_AVIF),
+    (b"M4A ", AUDIO_MP4),
+];
+
+/// Declared types that are compatible with a detected one: aliases, and formats built on the
+/// detected container
+const COMPATIBLE: &[(MediaType, &[(&str, &str)])] = &[
+    (IMAGE_JPEG, &[("image", "jpg"), ("image", "pjpeg")]),
+    (IMAGE_BMP, &[("image", "x-bmp"), ("image", "x-ms-bmp")]),
+    (AUDIO_MPEG, &[("audio", "mp3"), ("audio", "mpeg3")]),
+    (
+        AUDIO_WAV,
+        &[("audio", "wave"), ("audio", "x-wav"), ("audio", "vnd.wave")],
+    ),
+    (AUDIO_FLAC, &[("audio", "x-flac")]),
+    (
+        AUDIO_OGG,
+        &[("application", "ogg"), ("video", "ogg"), ("audio", "opus")],
+    ),
+    (AUDIO_MP4, &[("audio", "x-m4a"), ("audio", "aac")]),
+    (
+        VIDEO_MP4,
+        &[("audio", "mp4"), ("video", "x-m4v"), ("image", "heic")],
+    ),
+    (VIDEO_WEBM, &[("audio", "webm"), ("video", "x-matroska")]),
+    (IMAGE_HEIC, &[("image", "heif")]),
+    (
+        APPLICATION_ZIP,
+        &[
+            ("application", "x-zip-compressed"),
+            ("application", "java-archive"),
+        ],
+    ),
+    (APPLICATION_GZIP, &[("application", "x-gzip")]),
+];
+
+/// Detects the media type of `content` from its magic numbers, or `None` if it has no
+/// known signature (as is the case for most text)
+pub fn sniff(content: &[u8]) -> Option<MediaType> {
+    if let Some(kind) = content.get(8..12).filter(|_| content.starts_with(b"RIFF")) {
+        return RIFF_TYPES
+            .iter()
+            .find(|(riff_type, _)| kind == *riff_type)
+            .map(|(_, media_type)| media_type.clone());
+    }
+    if let Some(brand) = content.get(8..12).filter(|_| &content[4..8] == b"ftyp") {
+        return Some(
+            FTYP_BRANDS
+                .iter()
+                .find(|(ftyp_brand, _)| brand == *ftyp_brand)
+                .map_or(VIDEO_MP4, |(_, media_type)| media_type.clone()),
+        );
+    }
+    if let Some((_, media_type)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| content.starts_with(signature))
+    {
+        return Some(media_type.clone());
+    }
+    // the reserved fields of the BMP file header are zero
+    if content.starts_with(b"BM") && content.get(6..10) == Some(&[0; 4]) {
+        return Some(IMAGE_BMP);
+    }
+    // MPEG audio frame sync, without an ID3 tag
+    if content.len() >= 2 && content[0] == 0xFF && content[1] & 0xE6 == 0xE2 {
+        return Some(AUDIO_MPEG);
+    }
+    if is_html(content) {
+        return Some(MediaType::TEXT_HTML);
+    }
+    None
+}
+
+fn is_html(content: &[u8]) -> bool {
+    let start = content
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(content.len());
+    let content = &content[start..];
+    [b"<!doctype html".as_slice(), b"<html", b"<script"]
+        .iter()
+        .any(|tag| {
+            content
+                .get(..tag.len())
+                .is_some_and(|prefix| prefix.eq_ignore_ascii_case(tag))
+        })
+}
+
+fn is_compatible(declared: &MediaType, detected: &MediaType) -> bool {
+    if declared.same_essence(detected) {
+        return true;
+    }
+    let subtype = declared.subtype().to_ascii_lowercase();
+    if detected.same_essence(&APPLICATION_ZIP)
+        && declared.type_().eq_ignore_ascii_case("application")
+        && (subtype.ends_with("+zip")
+            || subtype.starts_with("vnd.openxmlformats-officedocument.")
+            || subtype.starts_with("vnd.oasis.opendocument."))
+    {
+        return true;
+    }
+    COMPATIBLE.iter().any(|(media_type, aliases)| {
+        media_type.same_essence(detected)
+            && aliases.iter().any(|(type_, subtype)| {
+                declared.type_().eq_ignore_ascii_case(type_)
+                    && declared.subtype().eq_ignore_ascii_case(subtype)
+            })
+    })
+}
+
+/// Whether `content` is UTF-8 without control characters other than whitespace, in which case
+/// short ASCII signatures (like `ID3` or `#!`) are only the start of a text
+fn is_plain_text(content: &[u8]) -> bool {
+    std::str::from_utf8(content).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || c.is_ascii_whitespace())
+    })
+}
+
+/// The result of comparing a declared content type with the sniffed one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SniffVerdict {
+    /// The content has the signature of the declared type, or of a compatible one
+    Match,
+    /// The content has no known signature, so the declared type can't be checked
+    Undetected,
+    /// The content has the signature of an incompatible type, and shouldn't be rendered as
+    /// the declared type
+    Mismatch {
+        declared: MediaType,
+        detected: MediaType,
+    },
+}
+
+impl SniffVerdict {
+    pub fn is_mismatch(&self) -> bool {
+        matches!(self, Self::Mismatch { .. })
+    }
+}
+
+/// Compares the `declared` content type with the one detected from `content`
+pub fn check_content_type(declared: &MediaType, content: &[u8]) -> SniffVerdict {
+    match sniff(content) {
+        None => SniffVerdict::Undetected,
+        Some(detected)
+            if is_compatible(declared, &detected)
+                || (declared.is_text() && is_plain_text(content)) =>
+        {
+            SniffVerdict::Match
+        }
+        Some(detected) => SniffVerdict::Mismatch {
+            declared: declared.clone(),
+            detected,
+        },
+    }
+}
+
+impl SinglePart {
+    pub fn check_content_type(&self) -> Result<SniffVerdict, MimiContentError> {
+        Ok(check_content_type(&self.media_type()?, &self.content))
+    }
+}
+
+impl ExternalPart {
+    /// Checks the declared content type against the `content` resolved with
+    /// [`ExternalPart::resolve`]
+    pub fn check_content_type(&self, content: &[u8]) -> Result<SniffVerdict, MimiContentError> {
+        Ok(check_content_type(&self.media_type()?, content))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniffs_magic_numbers() {
+        let sniffed = |content: &[u8]| sniff(content).map(|media_type| media_type.essence());
+        assert_eq!(
+            sniffed(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").as_deref(),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniffed(b"RIFF\x24\0\0\0WAVEfmt ").as_deref(),
+            Some("audio/wav")
+        );
+        assert_eq!(sniffed(b"RIFF\x24\0\0\0JUNK"), None);
+        assert_eq!(
+            sniffed(b"\0\0\0\x18ftypheic\0\0\0\0").as_deref(),
+            Some("image/heic")
+        );
+        assert_eq!(
+            sniffed(b"\0\0\0\x18ftypisom\0\0\0\0").as_deref(),
+            Some("video/mp4")
+        );
+        assert_eq!(sniffed(b"\xFF\xFB\x90\x00").as_deref(), Some("audio/mpeg"));
+        assert_eq!(
+            sniffed(b"  <!DOCTYPE HTML><p>hi").as_deref(),
+            Some("text/html")
+        );
+        assert_eq!(
+            sniffed(b"MZ\x90\0").as_deref(),
+            Some("application/x-msdownload")
+        );
+        assert_eq!(
+            sniffed(b"BM\x36\0\x0C\0\0\0\0\0\x36\0").as_deref(),
+            Some("image/bmp")
+        );
+        assert_eq!(sniffed(b"BMW"), None);
+        assert_eq!(sniffed(b"Hello, world"), None);
+        assert_eq!(sniffed(b""), None);
+    }
+
+    #[test]
+    fn checks_declared_content_types() {
+        let part = |content_type: &str, content: &[u8]| SinglePart {
+            content_type: content_type.into(),
+            content: content.to_vec().into(),
+        };
+        let verdict = |content_type: &str, content: &[u8]| {
+            part(content_type, content).check_content_type().unwrap()
+        };
+
+        assert_eq!(
+            verdict("image/png", b"\x89PNG\r\n\x1a\n"),
+            SniffVerdict::Match
+        );
+        assert_eq!(
+            verdict("image/jpg", b"\xFF\xD8\xFF\xE0"),
+            SniffVerdict::Match
+        );
+        assert_eq!(
+            verdict(
+                "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+                b"PK\x03\x04"
+            ),
+            SniffVerdict::Match
+        );
+        assert_eq!(
+            verdict("text/plain;charset=utf-8", b"<html><script>"),
+            SniffVerdict::Match
+        );
+        assert_eq!(
+            verdict("text/plain;charset=utf-8", b"hello"),
+            SniffVerdict::Undetected
+        );
+
+        let mismatch = verdict("image/png", b"MZ\x90\0\x03\0");
+        assert!(mismatch.is_mismatch());
+        assert_eq!(
+            mismatch,
+            SniffVerdict::Mismatch {
+                declared: MediaType::new("image", "png"),
+                detected: APPLICATION_MSDOWNLOAD,
+            }
+        );
+        assert_eq!(
+            verdict("text/plain", b"ID3 tags are..."),
+            SniffVerdict::Match
+        );
+        assert!(verdict("text/plain", b"ID3\x04\0\0\0\0\x01").is_mismatch());
+        assert!(verdict("image/svg+xml", b"<html><script>").is_mismatch());
+        assert!(part("", b"").check_content_type().is_err());
+    }
+}
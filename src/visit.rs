@@ -0,0 +1,404 @@
+use crate::{
+    BaseDispos, Disposition, MimiContent, MimiContentAsRef as _, MimiContentRef, NestedPart,
+    NestedPartContent, NestedPartContentRef, NestedPartRef,
+};
+
+/// A node of a nested part tree, implemented by `&NestedPart` and [`NestedPartRef`]
+pub trait PartTree<'a>: Clone + 'a {
+    fn disposition(&self) -> Disposition;
+    fn language(&self) -> &'a str;
+    /// The parts of a multipart, empty for any other content
+    fn children(&self) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator + 'a;
+
+    /// Looks up a part by its path, the indices through the `parts` of each multipart.
+    /// The empty path is `self`.
+    fn get(&self, path: &[usize]) -> Option<Self> {
+        path.iter()
+            .try_fold(self.clone(), |part, &index| part.children().nth(index))
+    }
+
+    /// Iterates over this part and all its descendants, depth-first
+    fn iter_parts(&self) -> Parts<'a, Self> {
+        Parts {
+            stack: vec![PathPart {
+                path: vec![],
+                disposition: self.disposition(),
+                language: self.language(),
+                part: self.clone(),
+            }],
+        }
+    }
+
+    /// Walks this part and its descendants depth-first until `visitor` stops
+    fn accept(&self, visitor: &mut impl Visitor<Self>) {
+        walk(
+            self.clone(),
+            &mut vec![],
+            Disposition::default(),
+            "",
+            visitor,
+        );
+    }
+}
+
+impl<'a> PartTree<'a> for &'a NestedPart {
+    fn disposition(&self) -> Disposition {
+        self.disposition
+    }
+
+    fn language(&self) -> &'a str {
+        &self.language
+    }
+
+    fn children(&self) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator + 'a {
+        NestedPart::children(self)
+    }
+}
+
+impl<'a> PartTree<'a> for NestedPartRef<'a> {
+    fn disposition(&self) -> Disposition {
+        *self.disposition
+    }
+
+    fn language(&self) -> &'a str {
+        self.language.as_str()
+    }
+
+    fn children(&self) -> impl DoubleEndedIterator<Item = Self> + ExactSizeIterator + 'a {
+        let parts = match &self.part_content {
+            NestedPartContentRef::MultiPart(multi) => multi.parts,
+            _ => &[],
+        };
+        parts.iter().map(NestedPart::as_ref)
+    }
+}
+
+/// Where a part is in its tree, and the disposition and language that apply to it
+///
+/// A part with an unspecified disposition or an empty language inherits the effective
+/// one of its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartInfo<'a> {
+    pub path: &'a [usize],
+    pub disposition: Disposition,
+    pub language: &'a str,
+}
+
+/// How to continue walking after visiting a part
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Walk {
+    #[default]
+    Continue,
+    SkipChildren,
+    Stop,
+}
+
+pub trait Visitor<P> {
+    fn visit_part(&mut self, part: P, info: &PartInfo<'_>) -> Walk;
+}
+
+impl<P, F: FnMut(P, &PartInfo<'_>) -> Walk> Visitor<P> for F {
+    fn visit_part(&mut self, part: P, info: &PartInfo<'_>) -> Walk {
+        self(part, info)
+    }
+}
+
+/// A visitor that may modify the parts, the children of a part are walked after it has been
+/// visited
+pub trait VisitorMut {
+    fn visit_part_mut(&mut self, part: &mut NestedPart, info: &PartInfo<'_>) -> Walk;
+}
+
+impl<F: FnMut(&mut NestedPart, &PartInfo<'_>) -> Walk> VisitorMut for F {
+    fn visit_part_mut(&mut self, part: &mut NestedPart, info: &PartInfo<'_>) -> Walk {
+        self(part, info)
+    }
+}
+
+fn effective_disposition(disposition: Disposition, parent: Disposition) -> Disposition {
+    match disposition {
+        Disposition::Base(BaseDispos::Unspecified) => parent,
+        disposition => disposition,
+    }
+}
+
+fn effective_language<'a>(language: &'a str, parent: &'a str) -> &'a str {
+    if language.is_empty() {
+        parent
+    } else {
+        language
+    }
+}
+
+fn walk<'a, P: PartTree<'a>>(
+    part: P,
+    path: &mut Vec<usize>,
+    parent_disposition: Disposition,
+    parent_language: &'a str,
+    visitor: &mut impl Visitor<P>,
+) -> bool {
+    let disposition = effective_disposition(part.disposition(), parent_disposition);
+    let language = effective_language(part.language(), parent_language);
+    let info = PartInfo {
+        path,
+        disposition,
+        language,
+    };
+    match visitor.visit_part(part.clone(), &info) {
+        Walk::Stop => return false,
+        Walk::SkipChildren => return true,
+        Walk::Continue => {}
+    }
+
+    for (index, child) in part.children().enumerate() {
+        path.push(index);
+        let keep_walking = walk(child, path, disposition, language, visitor);
+        path.pop();
+        if !keep_walking {
+            return false;
+        }
+    }
+    true
+}
+
+fn walk_mut(
+    part: &mut NestedPart,
+    path: &mut Vec<usize>,
+    parent_disposition: Disposition,
+    parent_language: &str,
+    visitor: &mut impl VisitorMut,
+) -> bool {
+    let language = effective_language(&part.language, parent_language).to_string();
+    let info = PartInfo {
+        path,
+        disposition: effective_disposition(part.disposition, parent_disposition),
+        language: &language,
+    };
+    match visitor.visit_part_mut(part, &info) {
+        Walk::Stop => return false,
+        Walk::SkipChildren => return true,
+        Walk::Continue => {}
+    }
+
+    // the visitor may have changed the part
+    let disposition = effective_disposition(part.disposition, parent_disposition);
+    let language = effective_language(&part.language, parent_language).to_string();
+    let NestedPartContent::MultiPart(multi) = &mut part.part_content else {
+        return true;
+    };
+    for (index, child) in multi.parts.iter_mut().enumerate() {
+        path.push(index);
+        let keep_walking = walk_mut(child, path, disposition, &language, visitor);
+        path.pop();
+        if !keep_walking {
+            return false;
+        }
+    }
+    true
+}
+
+/// A part yielded by [`PartTree::iter_parts`]
+#[derive(Debug, Clone)]
+pub struct PathPart<'a, P> {
+    pub path: Vec<usize>,
+    pub disposition: Disposition,
+    pub language: &'a str,
+    pub part: P,
+}
+
+pub struct Parts<'a, P> {
+    stack: Vec<PathPart<'a, P>>,
+}
+
+impl<'a, P: PartTree<'a>> Iterator for Parts<'a, P> {
+    type Item = PathPart<'a, P>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.stack.pop()?;
+        for (index, child) in next.part.children().enumerate().rev() {
+            let mut path = next.path.clone();
+            path.push(index);
+            self.stack.push(PathPart {
+                path,
+                disposition: effective_disposition(child.disposition(), next.disposition),
+                language: effective_language(child.language(), next.language),
+                part: child,
+            });
+        }
+        Some(next)
+    }
+}
+
+impl NestedPart {
+    /// See [`PartTree::get`]
+    pub fn get(&self, path: &[usize]) -> Option<&Self> {
+        PartTree::get(&self, path)
+    }
+
+    /// The parts of a multipart, empty for any other content
+    pub fn children(&self) -> std::slice::Iter<'_, Self> {
+        match &self.part_content {
+            NestedPartContent::MultiPart(multi) => multi.parts.iter(),
+            _ => [].iter(),
+        }
+    }
+
+    /// See [`PartTree::iter_parts`]
+    pub fn iter_parts(&self) -> Parts<'_, &Self> {
+        PartTree::iter_parts(&self)
+    }
+
+    /// See [`PartTree::accept`]
+    pub fn accept<'a>(&'a self, visitor: &mut impl Visitor<&'a Self>) {
+        PartTree::accept(&self, visitor);
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut Self> {
+        path.iter()
+            .try_fold(self, |part, &index| match &mut part.part_content {
+                NestedPartContent::MultiPart(multi) => multi.parts.get_mut(index),
+                _ => None,
+            })
+    }
+
+    /// Replaces the part at `path`, returning the previous one, or `None` if there is no
+    /// such part (in which case `part` is dropped)
+    pub fn replace(&mut self, path: &[usize], part: Self) -> Option<Self> {
+        self.get_mut(path)
+            .map(|existing| std::mem::replace(existing, part))
+    }
+
+    /// Walks this part and its descendants depth-first until `visitor` stops
+    pub fn accept_mut(&mut self, visitor: &mut impl VisitorMut) {
+        walk_mut(self, &mut vec![], Disposition::default(), "", visitor);
+    }
+}
+
+impl MimiContent {
+    /// See [`PartTree::get`]
+    pub fn get(&self, path: &[usize]) -> Option<&NestedPart> {
+        self.nested_part.get(path)
+    }
+
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut NestedPart> {
+        self.nested_part.get_mut(path)
+    }
+
+    /// See [`NestedPart::replace`]
+    pub fn replace(&mut self, path: &[usize], part: NestedPart) -> Option<NestedPart> {
+        self.nested_part.replace(path, part)
+    }
+
+    pub fn iter_parts(&self) -> Parts<'_, &NestedPart> {
+        self.nested_part.iter_parts()
+    }
+
+    pub fn accept<'a>(&'a self, visitor: &mut impl Visitor<&'a NestedPart>) {
+        self.nested_part.accept(visitor);
+    }
+
+    pub fn accept_mut(&mut self, visitor: &mut impl VisitorMut) {
+        self.nested_part.accept_mut(visitor);
+    }
+}
+
+impl<'a> MimiContentRef<'a> {
+    /// See [`PartTree::get`]
+    pub fn get(&self, path: &[usize]) -> Option<NestedPartRef<'a>> {
+        self.nested_part.get(path)
+    }
+
+    pub fn iter_parts(&self) -> Parts<'a, NestedPartRef<'a>> {
+        self.nested_part.iter_parts()
+    }
+
+    pub fn accept(&self, visitor: &mut impl Visitor<NestedPartRef<'a>>) {
+        self.nested_part.accept(visitor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Walk;
+    use crate::{
+        BaseDispos, Disposition, MimiContent, MimiContentAsRef as _, MimiContentDeserialize as _,
+        NestedPart, NestedPartContent, PartInfo,
+    };
+
+    fn multipart_3() -> MimiContent {
+        MimiContent::from_cbor_bytes(include_bytes!("../tests/examples/multipart-3.cbor")).unwrap()
+    }
+
+    #[test]
+    fn iterates_with_paths() {
+        let mimi_content = multipart_3();
+        let paths: Vec<_> = mimi_content.iter_parts().map(|part| part.path).collect();
+        assert_eq!(paths[0], Vec::<usize>::new());
+        assert_eq!(paths[1], vec![0]);
+        assert_eq!(paths[2], vec![0, 0]);
+
+        let mimi_content_ref = mimi_content.as_ref();
+        let ref_paths: Vec<_> = mimi_content_ref
+            .iter_parts()
+            .map(|part| part.path)
+            .collect();
+        assert_eq!(ref_paths, paths);
+
+        for path in &paths {
+            assert_eq!(
+                mimi_content.get(path).unwrap().as_ref(),
+                mimi_content_ref.get(path).unwrap()
+            );
+        }
+        assert!(mimi_content.get(&[42]).is_none());
+
+        let mut visited = vec![];
+        mimi_content.accept(&mut |_: &NestedPart, info: &PartInfo<'_>| {
+            visited.push(info.path.to_vec());
+            if info.path.len() == 1 {
+                Walk::SkipChildren
+            } else {
+                Walk::Continue
+            }
+        });
+        assert!(visited.iter().all(|path| path.len() <= 1));
+        assert_eq!(visited.len(), 1 + mimi_content.nested_part.children().len());
+    }
+
+    #[test]
+    fn inherits_disposition_and_language() {
+        let mut mimi_content = multipart_3();
+        mimi_content.nested_part.language = "fr".into();
+        mimi_content.nested_part.disposition = Disposition::Base(BaseDispos::Render);
+        for part in mimi_content.iter_parts() {
+            if part.part.language.is_empty() {
+                assert_eq!(part.language, "fr");
+            }
+            assert_ne!(part.disposition, Disposition::Base(BaseDispos::Unspecified));
+        }
+    }
+
+    #[test]
+    fn replaces_and_modifies_parts() {
+        let mut mimi_content = multipart_3();
+        let previous = mimi_content
+            .replace(&[0, 0], NestedPart::default())
+            .unwrap();
+        assert_ne!(previous, NestedPart::default());
+        assert_eq!(mimi_content.get(&[0, 0]), Some(&NestedPart::default()));
+        assert!(mimi_content.replace(&[0, 0, 0], previous).is_none());
+
+        mimi_content.accept_mut(&mut |part: &mut NestedPart, _: &PartInfo<'_>| {
+            if let NestedPartContent::SinglePart(single) = &mut part.part_content {
+                single.content = b"redacted".to_vec().into();
+            }
+            Walk::Continue
+        });
+        assert!(mimi_content
+            .iter_parts()
+            .all(|part| match &part.part.part_content {
+                NestedPartContent::SinglePart(single) => &*single.content == b"redacted",
+                _ => true,
+            }));
+    }
+}
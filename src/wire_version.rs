@@ -0,0 +1,165 @@
+use ciborium::Value;
+
+use crate::{
+    MimiContent, MimiContentDeserialize as _, MimiContentError, MimiContentSerialize as _,
+    NestedPartContentCardinality,
+};
+
+/// Index of `filename` within an ExternalPart nested part array
+const EXTERNAL_PART_FILENAME_INDEX: usize = 14;
+/// Index of the `parts` array within a MultiPart nested part array
+const MULTI_PART_PARTS_INDEX: usize = 4;
+/// Index of `nestedPart` within the MimiContent array
+const NESTED_PART_INDEX: usize = 6;
+
+/// Versions of the mimi-content draft that this crate can encode
+///
+/// The only wire difference between the supported drafts is the `filename` field of
+/// ExternalPart, which doesn't exist in draft-04.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WireVersion {
+    /// <https://www.ietf.org/archive/id/draft-ietf-mimi-content-04.html>
+    Draft04,
+    /// <https://www.ietf.org/archive/id/draft-ietf-mimi-content-06.html>
+    Draft06,
+}
+
+impl WireVersion {
+    pub const LATEST: Self = Self::Draft06;
+
+    /// Detects the version a serialized [`MimiContent`] was encoded with.
+    ///
+    /// Returns `Ok(None)` when the message is encoded identically in every supported version,
+    /// which is the case for messages without external parts.
+    pub fn detect(bytes: &[u8]) -> Result<Option<Self>, MimiContentError> {
+        let value: Value = ciborium::from_reader(bytes)?;
+        let mut detected = None;
+        if let Some(nested_part) = value
+            .as_array()
+            .and_then(|fields| fields.get(NESTED_PART_INDEX))
+        {
+            detect_nested_part(nested_part, &mut detected);
+        }
+        Ok(detected)
+    }
+}
+
+impl std::fmt::Display for WireVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Draft04 => write!(f, "draft-ietf-mimi-content-04"),
+            Self::Draft06 => write!(f, "draft-ietf-mimi-content-06"),
+        }
+    }
+}
+
+fn nested_part_cardinality(fields: &[Value]) -> Option<NestedPartContentCardinality> {
+    let cardinality = fields.get(2)?.as_integer()?;
+    NestedPartContentCardinality::try_from(u8::try_from(cardinality).ok()?).ok()
+}
+
+fn detect_nested_part(nested_part: &Value, detected: &mut Option<WireVersion>) {
+    let Some(fields) = nested_part.as_array() else {
+        return;
+    };
+    match nested_part_cardinality(fields) {
+        Some(NestedPartContentCardinality::ExternalPart) => {
+            let version = if fields.len() > EXTERNAL_PART_FILENAME_INDEX {
+                WireVersion::Draft06
+            } else {
+                WireVersion::Draft04
+            };
+            *detected = Some(detected.map_or(version, |detected| detected.max(version)));
+        }
+        Some(NestedPartContentCardinality::MultiPart) => {
+            for part in fields
+                .get(MULTI_PART_PARTS_INDEX)
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                detect_nested_part(part, detected);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn downgrade_nested_part(nested_part: &mut Value) -> Result<(), MimiContentError> {
+    let Some(fields) = nested_part.as_array_mut() else {
+        return Ok(());
+    };
+    match nested_part_cardinality(fields) {
+        Some(NestedPartContentCardinality::ExternalPart)
+            if fields.len() > EXTERNAL_PART_FILENAME_INDEX =>
+        {
+            // Trailing fields from newer versions follow the filename, and would take its place
+            if fields.len() > EXTERNAL_PART_FILENAME_INDEX + 1 {
+                return Err(MimiContentError::NotRepresentable {
+                    version: WireVersion::Draft04,
+                    reason: "external parts cannot carry fields newer than draft-06",
+                });
+            }
+            let filename = fields.remove(EXTERNAL_PART_FILENAME_INDEX);
+            if filename
+                .as_text()
+                .is_some_and(|filename| !filename.is_empty())
+            {
+                return Err(MimiContentError::NotRepresentable {
+                    version: WireVersion::Draft04,
+                    reason: "external parts cannot carry a filename",
+                });
+            }
+        }
+        Some(NestedPartContentCardinality::MultiPart) => {
+            if let Some(parts) = fields
+                .get_mut(MULTI_PART_PARTS_INDEX)
+                .and_then(Value::as_array_mut)
+            {
+                for part in parts {
+                    downgrade_nested_part(part)?;
+                }
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+impl MimiContent {
+    /// Serializes `self` for peers that implement `version`.
+    ///
+    /// Fails if the message uses a feature that `version` cannot represent, for example
+    /// a non-empty `filename` in draft-04 (clear it beforehand to downgrade anyway).
+    pub fn to_cbor_bytes_for(&self, version: WireVersion) -> Result<Vec<u8>, MimiContentError> {
+        match version {
+            WireVersion::Draft06 => self.to_cbor_bytes(),
+            WireVersion::Draft04 => {
+                let mut value = Value::serialized(self)?;
+                if let Some(nested_part) = value
+                    .as_array_mut()
+                    .and_then(|fields| fields.get_mut(NESTED_PART_INDEX))
+                {
+                    downgrade_nested_part(nested_part)?;
+                }
+                value.to_cbor_bytes()
+            }
+        }
+    }
+
+    /// Deserializes a message encoded with any supported version, also returning the detected
+    /// version (see [`WireVersion::detect`])
+    pub fn from_cbor_bytes_versioned(
+        bytes: &[u8],
+    ) -> Result<(Self, Option<WireVersion>), MimiContentError> {
+        Ok((Self::from_cbor_bytes(bytes)?, WireVersion::detect(bytes)?))
+    }
+}
+
+/// Re-encodes a serialized [`MimiContent`] of any supported version for `target`
+pub fn convert_wire_version(
+    bytes: &[u8],
+    target: WireVersion,
+) -> Result<Vec<u8>, MimiContentError> {
+    MimiContent::from_cbor_bytes(bytes)?.to_cbor_bytes_for(target)
+}
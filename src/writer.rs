@@ -0,0 +1,111 @@
+use std::io;
+
+/// An [`io::Write`] adapter feeding everything written into a digest or MAC, so that CBOR
+/// can be hashed without being buffered first
+#[derive(Debug, Clone, Default)]
+pub struct DigestWriter<D>(D);
+
+impl<D: digest::Update> DigestWriter<D> {
+    pub fn new(digest: D) -> Self {
+        Self(digest)
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> D {
+        self.0
+    }
+}
+
+impl<D: digest::Update> io::Write for DigestWriter<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.update(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// An [`io::Write`] adapter counting the bytes written to the inner writer
+#[derive(Debug, Clone, Default)]
+pub struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: io::Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// The number of bytes written so far
+    #[inline]
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use digest::Digest as _;
+
+    use crate::{
+        MimiContent, MimiContentAsRef as _, MimiContentDeserialize as _, MimiContentSerialize as _,
+    };
+
+    const EXAMPLES: [&[u8]; 4] = [
+        include_bytes!("../tests/examples/original.cbor"),
+        include_bytes!("../tests/examples/attachment.cbor"),
+        include_bytes!("../tests/examples/multipart-3.cbor"),
+        include_bytes!("../tests/examples/reaction.cbor"),
+    ];
+
+    #[test]
+    fn computes_encoded_len() {
+        for bytes in EXAMPLES {
+            let mimi_content = MimiContent::from_cbor_bytes(bytes).unwrap();
+            assert_eq!(mimi_content.encoded_len().unwrap(), bytes.len());
+            assert_eq!(mimi_content.as_ref().encoded_len().unwrap(), bytes.len());
+        }
+    }
+
+    #[test]
+    fn hashes_without_buffering() {
+        for bytes in EXAMPLES {
+            let mimi_content = MimiContent::from_cbor_bytes(bytes).unwrap();
+            assert_eq!(
+                &*mimi_content.hash::<sha2::Sha256>().unwrap(),
+                sha2::Sha256::digest(bytes).as_slice()
+            );
+
+            let streamed = mimi_content
+                .cbor_digest_update(sha2::Sha256::new())
+                .unwrap()
+                .finalize();
+            assert_eq!(streamed, sha2::Sha256::digest(bytes));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use mimi_content::{
+    delivery_report::MessageStatusReport, MimiContent, MimiContentAsRef as _,
+    MimiContentDeserialize as _, MimiContentSerialize as _,
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn allocations_during(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    f();
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+// A single test, so that no other test allocates concurrently
+#[test]
+fn ref_views_do_not_allocate() {
+    for bytes in [
+        &include_bytes!("examples/original.cbor")[..],
+        include_bytes!("examples/multipart-3.cbor"),
+        include_bytes!("examples/attachment.cbor"),
+    ] {
+        let mimi_content = MimiContent::from_cbor_bytes(bytes).unwrap();
+        assert_eq!(
+            allocations_during(|| {
+                std::hint::black_box(mimi_content.as_ref());
+            }),
+            0
+        );
+        assert_eq!(mimi_content.as_ref().to_cbor_bytes().unwrap(), bytes);
+        assert_eq!(mimi_content.encoded_len().unwrap(), bytes.len());
+    }
+
+    let report =
+        MessageStatusReport::from_cbor_bytes(include_bytes!("examples/report.cbor")).unwrap();
+    assert_eq!(
+        allocations_during(|| {
+            std::hint::black_box(report.as_ref());
+        }),
+        0
+    );
+}
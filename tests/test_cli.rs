@@ -0,0 +1,84 @@
+#![cfg(feature = "cli")]
+
+use std::{path::PathBuf, process::Command};
+
+fn cli(args: &[&str]) -> (bool, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mimi-content"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+/// A file in the temporary directory, removed on drop
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("mimi-content-cli-{}-{name}", std::process::id())))
+    }
+
+    fn path(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn build(name: &str, description: &str) -> (bool, String, TempFile) {
+    let description_file = TempFile::new(&format!("{name}.txt"));
+    let out = TempFile::new(&format!("{name}.cbor"));
+    std::fs::write(&description_file.0, description).unwrap();
+    let (success, _, stderr) = cli(&["build", description_file.path(), out.path()]);
+    (success, stderr, out)
+}
+
+#[test]
+fn validates_the_spec_examples() {
+    let (success, stdout, _) = cli(&["validate", "tests/examples/original.cbor"]);
+    assert!(success);
+    assert_eq!(stdout, "tests/examples/original.cbor: valid\n");
+}
+
+#[test]
+fn builds_messages_from_crlf_descriptions() {
+    let (success, stderr, out) = build(
+        "crlf",
+        "room: mimi://example.com/r/engineering_team\r\n\
+         content-type: text/plain;charset=utf-8\r\n\
+         \r\n\
+         Hello\r\n",
+    );
+    assert!(success, "{stderr}");
+    let (success, stdout, _) = cli(&["inspect", out.path()]);
+    assert!(success);
+    assert!(
+        stdout.contains("\"text/plain;charset=utf-8\", h'48656c6c6f'"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn rejects_invalid_message_ids() {
+    let id = format!("a\u{e9}{}", "0".repeat(61));
+    let (success, stderr, _) = build("bad-id", &format!("in-reply-to: {id}\n\nHi"));
+    assert!(!success);
+    assert!(stderr.contains("is not hexadecimal"), "{stderr}");
+    assert!(!stderr.contains("panicked"), "{stderr}");
+}
+
+#[test]
+fn renders_plain_text() {
+    let (success, stdout, _) = cli(&["render", "tests/examples/multipart-1.cbor", "--text"]);
+    assert!(success);
+    // `# Welcome!` in GFM-MIMI
+    assert_eq!(stdout.trim_end(), "Welcome!");
+}
@@ -0,0 +1,67 @@
+use mimi_content::{
+    delivery_report::MessageStatusReport,
+    derived::MessageDerivedValues,
+    edn::{MimiContentEdn as _, MimiContentFromEdn},
+    MimiContent, MimiContentDeserialize, MimiContentSerialize,
+};
+use pretty_assertions::assert_eq;
+
+fn edn_roundtrip<T>(bytes: &[u8])
+where
+    T: MimiContentSerialize + MimiContentDeserialize + MimiContentFromEdn,
+{
+    let value = T::from_cbor_bytes(bytes).unwrap();
+    for edn in [value.to_edn().unwrap(), value.to_edn_pretty().unwrap()] {
+        let parsed = T::from_edn(&edn).unwrap();
+        assert_eq!(parsed.to_cbor_bytes().unwrap(), bytes, "{edn}");
+    }
+}
+
+macro_rules! test_edn_roundtrip {
+    ($($struct:ty: $testname:ident => $cbor_file:literal),+ $(,)?) => {
+        $(
+            #[test]
+            fn $testname() {
+                edn_roundtrip::<$struct>(include_bytes!($cbor_file));
+            }
+        )+
+    };
+}
+
+test_edn_roundtrip!(
+    MessageStatusReport: edn_report => "./examples/report.cbor",
+    MessageDerivedValues: edn_implied_original => "./examples/implied-original.cbor",
+    MimiContent: edn_original => "./examples/original.cbor",
+    MimiContent: edn_reaction => "./examples/reaction.cbor",
+    MimiContent: edn_unlike => "./examples/unlike.cbor",
+    MimiContent: edn_reply => "./examples/reply.cbor",
+    MimiContent: edn_mention => "./examples/mention.cbor",
+    MimiContent: edn_mention_html => "./examples/mention-html.cbor",
+    MimiContent: edn_attachment => "./examples/attachment.cbor",
+    MimiContent: edn_conferencing => "./examples/conferencing.cbor",
+    MimiContent: edn_delete => "./examples/delete.cbor",
+    MimiContent: edn_edit => "./examples/edit.cbor",
+    MimiContent: edn_expiring => "./examples/expiring.cbor",
+    MimiContent: edn_multipart_1 => "./examples/multipart-1.cbor",
+    MimiContent: edn_multipart_2 => "./examples/multipart-2.cbor",
+    MimiContent: edn_multipart_3 => "./examples/multipart-3.cbor",
+);
+
+#[test]
+fn edn_authored_by_hand() {
+    let edn = r#"[
+        / salt / h'5eed9406c2545547ab6f09f20a18b003',
+        / replaces / null,
+        / topicId / h'',
+        / expires / null,
+        / inReplyTo / null,
+        / extensions / {1: "mimi://example.com/u/alice-smith", 2: "mimi://example.com/r/engineering_team"},
+        / nestedPart / [1, "", 1, "text/markdown;variant=GFM-MIMI", b64'SGkgZXZlcnlvbmUsIHdlIGp1c3Qgc2hpcHBlZCByZWxlYXNlIDIuMC4gX19Hb29kICB3b3JrX18h']
+    ]"#;
+
+    let mimi_content = MimiContent::from_edn(edn).unwrap();
+    assert_eq!(
+        mimi_content.to_cbor_bytes().unwrap(),
+        include_bytes!("./examples/original.cbor")
+    );
+}
@@ -0,0 +1,164 @@
+use mimi_content::{
+    BaseDispos, Disposition, MediaType, MimiContent, MimiContentDeserialize as _, MimiContentError,
+    MultiPart, NestedPart, NestedPartContent, PartSemantics, SinglePart,
+};
+
+fn multipart_1() -> MimiContent {
+    MimiContent::from_cbor_bytes(include_bytes!("examples/multipart-1.cbor")).unwrap()
+}
+
+fn single_part(nested_part: &NestedPart) -> &SinglePart {
+    match &nested_part.part_content {
+        NestedPartContent::SinglePart(single) => single,
+        other => panic!("expected a single part, got {other:?}"),
+    }
+}
+
+#[test]
+fn keeps_renderable_content() {
+    let mimi_content = multipart_1();
+    let accepted = [MediaType::new("text", "*")];
+    assert!(mimi_content.is_renderable_by(&accepted));
+    assert_eq!(mimi_content.downgrade_for(&accepted).unwrap(), mimi_content);
+}
+
+#[test]
+fn rejects_unrenderable_content() {
+    assert!(matches!(
+        multipart_1().downgrade_for(&[MediaType::new("image", "*")]),
+        Err(MimiContentError::NotRenderable(_))
+    ));
+}
+
+#[cfg(feature = "gfm-mimi")]
+#[test]
+fn converts_gfm_mimi_to_plain_text() {
+    let accepted = [MediaType::TEXT_PLAIN];
+    let mimi_content = multipart_1();
+    assert!(!mimi_content.is_renderable_by(&accepted));
+
+    let downgraded = mimi_content.downgrade_for(&accepted).unwrap();
+    let single = single_part(&downgraded.nested_part);
+    assert_eq!(single.media_type().unwrap(), MediaType::TEXT_PLAIN_UTF8);
+    assert!(!single.text().unwrap().contains("**"));
+    assert!(downgraded.is_renderable_by(&accepted));
+}
+
+fn part(content_type: &str) -> NestedPart {
+    NestedPart {
+        part_content: NestedPartContent::SinglePart(SinglePart {
+            content_type: content_type.into(),
+            content: b"...".to_vec().into(),
+        }),
+        ..Default::default()
+    }
+}
+
+fn multipart(part_semantics: PartSemantics) -> NestedPart {
+    NestedPart {
+        part_content: NestedPartContent::MultiPart(MultiPart {
+            part_semantics,
+            parts: vec![part("text/plain"), part("image/png")],
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn chooses_an_accepted_alternative() {
+    let choose_one = NestedPart {
+        part_content: NestedPartContent::MultiPart(MultiPart {
+            part_semantics: PartSemantics::ChooseOne,
+            parts: vec![
+                multipart(PartSemantics::ProcessAll),
+                part("application/x-unknown"),
+            ],
+        }),
+        ..Default::default()
+    };
+
+    let accepted = [MediaType::TEXT_PLAIN];
+    assert!(!choose_one.is_renderable_by(&accepted));
+    let downgraded = choose_one.downgrade_for(&accepted).unwrap();
+    let NestedPartContent::MultiPart(multi) = &downgraded.part_content else {
+        panic!("expected the processAll alternative");
+    };
+    assert_eq!(multi.part_semantics, PartSemantics::ProcessAll);
+    assert_eq!(multi.parts, vec![part("text/plain")]);
+}
+
+#[test]
+fn drops_optional_parts() {
+    let accepted = [MediaType::TEXT_PLAIN];
+    let downgraded = multipart(PartSemantics::ProcessAll)
+        .downgrade_for(&accepted)
+        .unwrap();
+    let NestedPartContent::MultiPart(multi) = &downgraded.part_content else {
+        panic!("expected a multipart");
+    };
+    assert_eq!(multi.parts.len(), 1);
+    assert_eq!(&*single_part(&multi.parts[0]).content_type, "text/plain");
+
+    assert!(matches!(
+        multipart(PartSemantics::SingleUnit).downgrade_for(&accepted),
+        Err(MimiContentError::NotRenderable(_))
+    ));
+}
+
+#[test]
+fn chosen_alternatives_keep_what_they_inherited() {
+    let choose_one = NestedPart {
+        disposition: Disposition::Base(BaseDispos::Reaction),
+        language: "fr".to_string().into(),
+        part_content: NestedPartContent::MultiPart(MultiPart {
+            part_semantics: PartSemantics::ChooseOne,
+            parts: vec![
+                part("image/png"),
+                NestedPart {
+                    language: "".to_string().into(),
+                    ..multipart(PartSemantics::ProcessAll)
+                },
+            ],
+        }),
+        ..Default::default()
+    };
+
+    let downgraded = choose_one.downgrade_for(&[MediaType::TEXT_PLAIN]).unwrap();
+    assert_eq!(
+        downgraded.disposition,
+        Disposition::Base(BaseDispos::Reaction)
+    );
+    assert_eq!(&*downgraded.language, "fr");
+    let NestedPartContent::MultiPart(multi) = &downgraded.part_content else {
+        panic!("expected the processAll alternative");
+    };
+    assert_eq!(multi.parts, vec![part("text/plain")]);
+}
+
+#[test]
+fn keeps_single_unit_requirements_in_optional_parts() {
+    let process_all = |parts| NestedPart {
+        part_content: NestedPartContent::MultiPart(MultiPart {
+            part_semantics: PartSemantics::ProcessAll,
+            parts,
+        }),
+        ..Default::default()
+    };
+    let accepted = [MediaType::TEXT_PLAIN];
+
+    let unmet = process_all(vec![
+        part("text/plain"),
+        process_all(vec![multipart(PartSemantics::SingleUnit)]),
+    ]);
+    assert!(matches!(
+        unmet.downgrade_for(&accepted),
+        Err(MimiContentError::NotRenderable(_))
+    ));
+
+    let optional = process_all(vec![
+        part("text/plain"),
+        process_all(vec![part("image/png")]),
+    ]);
+    let downgraded = optional.downgrade_for(&accepted).unwrap();
+    assert_eq!(downgraded, process_all(vec![part("text/plain")]));
+}
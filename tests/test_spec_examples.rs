@@ -163,6 +163,7 @@ test_mimi_content!(
 );
 
 #[test]
+#[allow(clippy::vec_init_then_push)]
 fn repro_dual_singlepart_in_multipart_usecase() {
     use mimi_content::{
         MimiContent, MimiContentDeserialize, MimiContentSerialize, MultiPart, NestedPart,
@@ -0,0 +1,153 @@
+use mimi_content::{
+    convert_wire_version, MimiContent, MimiContentDeserialize as _, MimiContentError,
+    NestedPartContent, WireVersion,
+};
+use pretty_assertions::assert_eq;
+
+const CONFERENCING_DRAFT_06: &[u8] = include_bytes!("./examples/conferencing.cbor");
+
+/// Draft-04 only differs from draft-06 by the trailing `filename` of external parts, so
+/// the draft-04 encodings are built from the draft-06 examples by removing it, without
+/// going through the crate
+fn without_filenames(draft_06: &[u8]) -> Vec<u8> {
+    fn remove_filenames(nested_part: &mut ciborium::Value) {
+        let fields = nested_part.as_array_mut().unwrap();
+        match fields[2].as_integer().map(i128::from) {
+            // [disposition, language, 2, contentType, ..., description, filename]
+            Some(2) => {
+                assert_eq!(fields.len(), 15);
+                fields.pop();
+            }
+            // [disposition, language, 3, partSemantics, parts]
+            Some(3) => fields[4]
+                .as_array_mut()
+                .unwrap()
+                .iter_mut()
+                .for_each(remove_filenames),
+            _ => {}
+        }
+    }
+
+    let mut value: ciborium::Value = ciborium::from_reader(draft_06).unwrap();
+    remove_filenames(&mut value.as_array_mut().unwrap()[6]);
+    let mut bytes = vec![];
+    ciborium::into_writer(&value, &mut bytes).unwrap();
+    bytes
+}
+
+#[test]
+fn detects_versions() {
+    assert_eq!(
+        WireVersion::detect(&without_filenames(CONFERENCING_DRAFT_06)).unwrap(),
+        Some(WireVersion::Draft04)
+    );
+    assert_eq!(
+        WireVersion::detect(CONFERENCING_DRAFT_06).unwrap(),
+        Some(WireVersion::Draft06)
+    );
+    assert_eq!(
+        WireVersion::detect(include_bytes!("./examples/attachment.cbor")).unwrap(),
+        Some(WireVersion::Draft06)
+    );
+    assert_eq!(
+        WireVersion::detect(include_bytes!("./examples/multipart-3.cbor")).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn converts_between_versions() {
+    let conferencing_draft_04 = without_filenames(CONFERENCING_DRAFT_06);
+    assert_eq!(
+        convert_wire_version(&conferencing_draft_04, WireVersion::Draft06).unwrap(),
+        CONFERENCING_DRAFT_06
+    );
+    assert_eq!(
+        convert_wire_version(CONFERENCING_DRAFT_06, WireVersion::Draft04).unwrap(),
+        conferencing_draft_04
+    );
+
+    let (draft_04, version) =
+        MimiContent::from_cbor_bytes_versioned(&conferencing_draft_04).unwrap();
+    assert_eq!(version, Some(WireVersion::Draft04));
+    assert_eq!(
+        draft_04,
+        MimiContent::from_cbor_bytes(CONFERENCING_DRAFT_06).unwrap()
+    );
+}
+
+/// Spec examples, the ones without external parts being encoded identically in both versions
+const EXAMPLES: &[(&str, &[u8])] = &[
+    ("conferencing", CONFERENCING_DRAFT_06),
+    ("multipart-1", include_bytes!("./examples/multipart-1.cbor")),
+    ("multipart-2", include_bytes!("./examples/multipart-2.cbor")),
+    ("multipart-3", include_bytes!("./examples/multipart-3.cbor")),
+    ("reaction", include_bytes!("./examples/reaction.cbor")),
+];
+
+#[test]
+fn encodes_draft_04() {
+    for &(name, draft_06) in EXAMPLES {
+        let draft_04 = without_filenames(draft_06);
+        let (mimi_content, version) = MimiContent::from_cbor_bytes_versioned(&draft_04).unwrap();
+        assert_ne!(version, Some(WireVersion::Draft06), "{name}");
+        assert_eq!(
+            mimi_content
+                .to_cbor_bytes_for(WireVersion::Draft04)
+                .unwrap(),
+            draft_04,
+            "{name}"
+        );
+        assert_eq!(
+            convert_wire_version(&draft_04, WireVersion::Draft06).unwrap(),
+            draft_06,
+            "{name}"
+        );
+    }
+
+    // the attachment has a filename, which draft-04 can't carry
+    let draft_06 = include_bytes!("./examples/attachment.cbor");
+    let mut attachment = MimiContent::from_cbor_bytes(draft_06).unwrap();
+    let NestedPartContent::ExternalPart(external) = &mut attachment.nested_part.part_content else {
+        panic!("expected an external part");
+    };
+    external.filename = String::new().into();
+    assert_eq!(
+        attachment.to_cbor_bytes_for(WireVersion::Draft04).unwrap(),
+        without_filenames(draft_06)
+    );
+}
+
+#[test]
+fn refuses_lossy_downgrade() {
+    let result = convert_wire_version(
+        include_bytes!("./examples/attachment.cbor"),
+        WireVersion::Draft04,
+    );
+    assert!(matches!(
+        result,
+        Err(MimiContentError::NotRepresentable {
+            version: WireVersion::Draft04,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn refuses_to_downgrade_trailing_fields() {
+    let mut attachment = MimiContent::from_cbor_bytes(&without_filenames(include_bytes!(
+        "./examples/attachment.cbor"
+    )))
+    .unwrap();
+    attachment
+        .nested_part
+        .unknown_fields
+        .push(ciborium::Value::Text("from the future".into()).into());
+    assert!(matches!(
+        attachment.to_cbor_bytes_for(WireVersion::Draft04),
+        Err(MimiContentError::NotRepresentable {
+            version: WireVersion::Draft04,
+            ..
+        })
+    ));
+}